@@ -1,22 +1,77 @@
 use std::collections::HashMap;
+use std::hash::Hash;
 use std::str;
 
 use lib::error::Fail;
+use lib::iterplus::CheckedAdd;
 
-fn first_repeat(c: &[i64]) -> Option<i64> {
-    let mut seen: HashMap<i64, usize> = HashMap::new();
-    seen.insert(0, 1);
-    let mut current = 0;
-    for n in c.iter().cycle() {
-        current += n;
-        seen.entry(current)
+#[cfg(feature = "bigint")]
+type Frequency = num_bigint::BigInt;
+#[cfg(all(feature = "wide-frequency", not(feature = "bigint")))]
+type Frequency = i128;
+#[cfg(not(any(feature = "wide-frequency", feature = "bigint")))]
+type Frequency = i64;
+
+fn overflow_at(line_number: usize) -> Fail {
+    Fail(format!("frequency overflowed at line {line_number}"))
+}
+
+fn total_change<T>(changes: &[i64]) -> Result<T, Fail>
+where
+    T: CheckedAdd + From<i64>,
+{
+    changes
+        .iter()
+        .enumerate()
+        .try_fold(T::from(0), |total, (i, n)| {
+            total
+                .checked_add(T::from(*n))
+                .ok_or_else(|| overflow_at(i + 1))
+        })
+}
+
+#[test]
+fn test_total_change() {
+    assert_eq!(
+        total_change::<i64>(&get_input("+1\n-2\n+3\n+1\n").expect("wanted valid input"))
+            .expect("no overflow"),
+        3
+    );
+    assert_eq!(total_change::<i64>(&[]).expect("no overflow"), 0);
+}
+
+#[test]
+fn test_total_change_overflow() {
+    let changes = [1, i64::MAX - 1, 1, -5];
+    match total_change::<i64>(&changes) {
+        Err(Fail(msg)) => assert_eq!(msg, "frequency overflowed at line 3"),
+        Ok(n) => panic!("expected overflow, got {n}"),
+    }
+    assert_eq!(
+        total_change::<i128>(&changes).expect("no overflow"),
+        i128::from(i64::MAX) - 4
+    );
+}
+
+fn first_repeat<T>(c: &[i64]) -> Result<Option<T>, Fail>
+where
+    T: CheckedAdd + From<i64> + Hash + Eq + Clone,
+{
+    let mut seen: HashMap<T, usize> = HashMap::new();
+    seen.insert(T::from(0), 1);
+    let mut current = T::from(0);
+    for (i, n) in c.iter().enumerate().cycle() {
+        current = current
+            .checked_add(T::from(*n))
+            .ok_or_else(|| overflow_at(i + 1))?;
+        seen.entry(current.clone())
             .and_modify(|counter| *counter += 1)
             .or_insert(1);
         if let Some(2) = seen.get(&current) {
-            return Some(current);
+            return Ok(Some(current));
         }
     }
-    None
+    Ok(None)
 }
 
 fn get_input(text: &str) -> Result<Vec<i64>, Fail> {
@@ -31,19 +86,34 @@ fn get_input(text: &str) -> Result<Vec<i64>, Fail> {
 #[test]
 fn test_part2() {
     assert_eq!(
-        first_repeat(&get_input("+1\n-1\n").expect("wanted valid input")),
+        first_repeat::<i64>(&get_input("+1\n-1\n").expect("wanted valid input"))
+            .expect("no overflow"),
         Some(0)
     );
     assert_eq!(
-        first_repeat(&get_input("+3\n+3\n+4\n-2\n-4\n").expect("wanted valid input")),
+        first_repeat::<i64>(&get_input("+3\n+3\n+4\n-2\n-4\n").expect("wanted valid input"))
+            .expect("no overflow"),
         Some(10)
     );
 }
 
+#[test]
+fn test_part2_overflow() {
+    // The running total never repeats, so we keep going round until
+    // it overflows, which happens on the second line of some pass.
+    let changes = [-1, i64::MAX / 4];
+    match first_repeat::<i64>(&changes) {
+        Err(Fail(msg)) => assert_eq!(msg, "frequency overflowed at line 2"),
+        Ok(n) => panic!("expected overflow, got {n:?}"),
+    }
+}
+
 fn main() {
     let text = str::from_utf8(include_bytes!("input.txt")).unwrap();
     let frequencies = get_input(text).expect("wanted valid input");
-    let first_repeat: Option<i64> = first_repeat(&frequencies);
-    println!("Day 01 part 1: {}", frequencies.iter().sum::<i64>());
+    let total: Frequency = total_change(&frequencies).expect("total should not overflow");
+    let first_repeat: Option<Frequency> =
+        first_repeat(&frequencies).expect("running total should not overflow");
+    println!("Day 01 part 1: {}", total);
     println!("Day 01 part 2: {}", first_repeat.expect("expected repeat"));
 }
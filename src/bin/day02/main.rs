@@ -1,35 +1,86 @@
+use clap::{Arg, Command};
 use itertools::Itertools;
 use lib::error::Fail;
-use std::collections::HashMap;
+use lib::strdist::{common_letters, hamming};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
 use std::str;
+use unicode_segmentation::UnicodeSegmentation;
 
-fn letters_by_freq(s: &str) -> HashMap<usize, Vec<char>> {
-    let mut freqs_by_letter: HashMap<char, usize> = HashMap::new();
-    for ch in s.chars() {
+/// How to split a box ID into letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Counting {
+    Chars,
+    Graphemes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChecksumOptions {
+    /// Letter frequencies we are interested in; the puzzle uses 2
+    /// and 3.
+    frequencies: BTreeSet<usize>,
+    /// Whether "A" and "a" count as the same letter.
+    fold_case: bool,
+    counting: Counting,
+}
+
+impl Default for ChecksumOptions {
+    fn default() -> ChecksumOptions {
+        ChecksumOptions {
+            frequencies: [2, 3].into_iter().collect(),
+            fold_case: false,
+            counting: Counting::Chars,
+        }
+    }
+}
+
+fn letters_by_freq(s: &str, options: &ChecksumOptions) -> HashMap<usize, Vec<String>> {
+    let letters: Box<dyn Iterator<Item = &str>> = match options.counting {
+        Counting::Chars => Box::new(
+            s.char_indices()
+                .map(|(pos, ch)| &s[pos..pos + ch.len_utf8()]),
+        ),
+        Counting::Graphemes => Box::new(s.graphemes(true)),
+    };
+    let mut freqs_by_letter: HashMap<String, usize> = HashMap::new();
+    for letter in letters {
+        let letter = if options.fold_case {
+            letter.to_lowercase()
+        } else {
+            letter.to_string()
+        };
         freqs_by_letter
-            .entry(ch)
+            .entry(letter)
             .and_modify(|entry| *entry += 1)
             .or_insert(1);
     }
-    let mut letters_by_freq: HashMap<usize, Vec<char>> = HashMap::new();
-    for (letter, freq) in freqs_by_letter.iter() {
-        letters_by_freq
-            .entry(*freq)
-            .and_modify(|letters| letters.push(*letter))
-            .or_insert_with(|| vec![*letter]);
+    let mut letters_by_freq: HashMap<usize, Vec<String>> = HashMap::new();
+    for (letter, freq) in freqs_by_letter.into_iter() {
+        letters_by_freq.entry(freq).or_default().push(letter);
     }
     letters_by_freq
 }
 
-fn two_or_three(s: &str) -> (Option<usize>, Option<usize>) {
-    let lbf = letters_by_freq(s);
-    let two = lbf.get(&2).map(|letters| letters.len());
-    let three = lbf.get(&3).map(|letters| letters.len());
-    (two, three)
+/// Returns, for each frequency of interest, the number of distinct
+/// letters which occur exactly that many times in `s`.  Frequencies
+/// which no letter has are omitted.
+fn letter_counts_by_freq(s: &str, options: &ChecksumOptions) -> BTreeMap<usize, usize> {
+    let lbf = letters_by_freq(s, options);
+    options
+        .frequencies
+        .iter()
+        .filter_map(|freq| lbf.get(freq).map(|letters| (*freq, letters.len())))
+        .collect()
 }
 
 #[test]
 fn test_two_or_three() {
+    let options = ChecksumOptions::default();
+    let two_or_three = |s: &str| -> (Option<usize>, Option<usize>) {
+        let counts = letter_counts_by_freq(s, &options);
+        (counts.get(&2).copied(), counts.get(&3).copied())
+    };
     assert_eq!(two_or_three("abcdef"), (None, None));
     assert_eq!(two_or_three("bababc"), (Some(1), Some(1)));
     assert_eq!(two_or_three("abbcde"), (Some(1), None));
@@ -39,21 +90,95 @@ fn test_two_or_three() {
     assert_eq!(two_or_three("ababab"), (None, Some(2)));
 }
 
+#[test]
+fn test_letter_counts_by_freq() {
+    let options = ChecksumOptions::default();
+    let counts = |s: &str| -> Vec<(usize, usize)> {
+        letter_counts_by_freq(s, &options).into_iter().collect()
+    };
+    assert_eq!(counts("abcdef"), vec![]);
+    assert_eq!(counts("bababc"), vec![(2, 1), (3, 1)]);
+    assert_eq!(counts("abbcde"), vec![(2, 1)]);
+    assert_eq!(counts("abcccd"), vec![(3, 1)]);
+    assert_eq!(counts("aabcdd"), vec![(2, 2)]);
+    assert_eq!(counts("abcdee"), vec![(2, 1)]);
+    assert_eq!(counts("ababab"), vec![(3, 2)]);
+}
+
+#[test]
+fn test_letter_counts_by_freq_options() {
+    let mut options = ChecksumOptions {
+        frequencies: [1, 4].into_iter().collect(),
+        ..ChecksumOptions::default()
+    };
+    assert_eq!(
+        letter_counts_by_freq("aAaAb", &options),
+        [(1, 1)].into_iter().collect()
+    );
+    options.fold_case = true;
+    assert_eq!(
+        letter_counts_by_freq("aAaAb", &options),
+        [(1, 1), (4, 1)].into_iter().collect()
+    );
+
+    // "e" followed by a combining acute accent is two chars but only
+    // one grapheme.
+    let options = ChecksumOptions {
+        frequencies: [2].into_iter().collect(),
+        ..ChecksumOptions::default()
+    };
+    assert_eq!(
+        letter_counts_by_freq("e\u{301}e\u{301}", &options),
+        [(2, 2)].into_iter().collect()
+    );
+    let options = ChecksumOptions {
+        counting: Counting::Graphemes,
+        ..options
+    };
+    assert_eq!(
+        letter_counts_by_freq("e\u{301}e\u{301}", &options),
+        [(2, 1)].into_iter().collect()
+    );
+}
+
 fn get_ids(s: &str) -> Vec<&str> {
     s.split_terminator('\n').collect()
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Checksum {
+    /// For each frequency of interest, the number of IDs having at
+    /// least one letter which occurs exactly that many times.
+    by_frequency: BTreeMap<usize, usize>,
+}
+
+impl Checksum {
+    /// The product of the per-frequency counts, or `None` if it
+    /// overflows.
+    fn product(&self) -> Option<usize> {
+        self.by_frequency
+            .values()
+            .try_fold(1_usize, |total, n| total.checked_mul(*n))
+    }
+}
+
+fn frequency_checksum(ids: &[&str], options: &ChecksumOptions) -> Checksum {
+    let mut by_frequency: BTreeMap<usize, usize> =
+        options.frequencies.iter().map(|freq| (*freq, 0)).collect();
+    for id in ids.iter() {
+        for freq in letter_counts_by_freq(id, options).keys() {
+            if let Some(n) = by_frequency.get_mut(freq) {
+                *n += 1;
+            }
+        }
+    }
+    Checksum { by_frequency }
+}
+
 fn checksum(ids: &[&str]) -> usize {
-    let (count_two, count_three) = ids.iter().map(|line| two_or_three(line)).fold(
-        (0, 0),
-        |(total_two, total_three), (two, three)| {
-            (
-                total_two + if two.is_some() { 1 } else { 0 },
-                total_three + if three.is_some() { 1 } else { 0 },
-            )
-        },
-    );
-    count_two * count_three
+    frequency_checksum(ids, &ChecksumOptions::default())
+        .product()
+        .expect("checksum should not overflow")
 }
 
 #[test]
@@ -62,26 +187,56 @@ fn test_checksum() {
     assert_eq!(checksum(&ids), 12);
 }
 
-fn diffcount(left: &str, right: &str) -> usize {
-    left.chars()
-        .zip(right.chars())
-        .filter(|(l, r)| l != r)
-        .count()
+#[test]
+fn test_frequency_checksum() {
+    let ids = get_ids("abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab\naaaabb\n");
+    let options = ChecksumOptions {
+        frequencies: [2, 3, 4].into_iter().collect(),
+        ..ChecksumOptions::default()
+    };
+    let result = frequency_checksum(&ids, &options);
+    assert_eq!(
+        result.by_frequency,
+        [(2, 5), (3, 3), (4, 1)].into_iter().collect()
+    );
+    assert_eq!(result.product(), Some(15));
+
+    let options = ChecksumOptions {
+        frequencies: BTreeSet::new(),
+        ..ChecksumOptions::default()
+    };
+    let result = frequency_checksum(&ids, &options);
+    assert!(result.by_frequency.is_empty());
+    assert_eq!(result.product(), Some(1));
 }
 
 #[test]
 fn test_diffcount() {
-    assert_eq!(diffcount("abcde", "axcye"), 2);
-    assert_eq!(diffcount("fghij", "fguij"), 1);
+    assert_eq!(hamming("abcde", "axcye"), Ok(2));
+    assert_eq!(hamming("fghij", "fguij"), Ok(1));
+}
+
+#[test]
+fn test_hamming_rejects_unequal_lengths() {
+    // Zipping the letters together would make these look identical.
+    assert!(hamming("abc", "abcdef").is_err());
+    assert_eq!(
+        find_id_pairs(&["abc", "abcdef", "abd"]),
+        vec![("abc", "abd")]
+    );
 }
 
+/// The original quadratic search, kept as a reference for
+/// `bench_find_id_pairs`.
+#[cfg(test)]
+#[allow(clippy::needless_lifetimes)]
 fn find_id_pair<'a, 'b>(ids: &'a [&'b str]) -> Option<(&'b str, &'b str)> {
     for (left, right) in ids
         .iter()
         .cartesian_product(ids.iter())
         .filter(|(l, r)| l != r)
     {
-        if diffcount(left, right) == 1 {
+        if hamming(left, right) == Ok(1) {
             return Some((left, right));
         }
     }
@@ -96,17 +251,175 @@ fn test_id_pair() {
     assert_eq!(find_id_pair(&ids), Some(("fghij", "fguij")));
 }
 
+/// The base of the polynomial hash used by
+/// `find_id_pairs_with_k_differences` (the 64-bit FNV prime).
+const HASH_BASE: u64 = 0x0000_0100_0000_01b3;
+
+/// Finds all pairs of IDs which differ in exactly `k` positions.
+///
+/// Each ID is given a polynomial hash of the hashes of its letters.
+/// Treating a letter as masked out just subtracts its term from that
+/// hash, so for each choice of `k` positions the masked hash of an ID
+/// takes O(k) time to find, and two IDs can only land in the same
+/// bucket if they differ at no more than those `k` positions.  This
+/// takes expected O(n·L + n·C(L, k)·k) time for `n` IDs of length `L`
+/// (O(n·L) when `k = 1`), plus O(L) for each candidate pair checked,
+/// instead of comparing every pair.  Pairs are returned in the order
+/// in which they occur in `ids`.
+fn find_id_pairs_with_k_differences<'b>(ids: &[&'b str], k: usize) -> Vec<(&'b str, &'b str)> {
+    // Only IDs of the same length can be compared, so group them
+    // first.  Each group keeps the index of each ID in `ids` and the
+    // hash of each of its letters.
+    let mut by_length: BTreeMap<usize, Vec<(usize, Vec<u64>)>> = BTreeMap::new();
+    for (index, id) in ids.iter().enumerate() {
+        let letters: Vec<u64> = id
+            .graphemes(true)
+            .map(|letter| {
+                let mut hasher = DefaultHasher::new();
+                letter.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        by_length
+            .entry(letters.len())
+            .or_default()
+            .push((index, letters));
+    }
+    let mut pairs: BTreeSet<(usize, usize)> = BTreeSet::new();
+    for (len, group) in by_length.iter() {
+        // weights[i] multiplies the hash of the letter at position i.
+        let mut weights = vec![1_u64; *len];
+        for i in (1..*len).rev() {
+            weights[i - 1] = weights[i].wrapping_mul(HASH_BASE);
+        }
+        let term = |letters: &[u64], i: usize| letters[i].wrapping_mul(weights[i]);
+        let full: Vec<u64> = group
+            .iter()
+            .map(|(_, letters)| (0..*len).fold(0_u64, |h, i| h.wrapping_add(term(letters, i))))
+            .collect();
+        for masked in (0..*len).combinations(k) {
+            let mut buckets: HashMap<u64, Vec<usize>> = HashMap::with_capacity(group.len());
+            for ((index, letters), full) in group.iter().zip(full.iter()) {
+                let key = masked
+                    .iter()
+                    .fold(*full, |h, m| h.wrapping_sub(term(letters, *m)));
+                buckets.entry(key).or_default().push(*index);
+            }
+            // Hash collisions are harmless, because we check each
+            // candidate pair anyway.
+            for bucket in buckets.values() {
+                for (i, j) in bucket.iter().tuple_combinations() {
+                    if hamming(ids[*i], ids[*j]) == Ok(k) {
+                        pairs.insert((*i, *j));
+                    }
+                }
+            }
+        }
+    }
+    pairs.into_iter().map(|(i, j)| (ids[i], ids[j])).collect()
+}
+
+fn find_id_pairs<'b>(ids: &[&'b str]) -> Vec<(&'b str, &'b str)> {
+    find_id_pairs_with_k_differences(ids, 1)
+}
+
+#[test]
+fn test_find_id_pairs() {
+    let ids = vec![
+        "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
+    ];
+    assert_eq!(find_id_pairs(&ids), vec![("fghij", "fguij")]);
+    assert_eq!(
+        find_id_pairs(&["abc", "abd", "xbc", "abc", "abcd"]),
+        vec![
+            ("abc", "abd"),
+            ("abc", "xbc"),
+            ("abd", "abc"),
+            ("xbc", "abc")
+        ]
+    );
+    assert_eq!(find_id_pairs(&["Łódź", "Łodź"]), vec![("Łódź", "Łodź")]);
+    assert!(find_id_pairs(&[]).is_empty());
+}
+
+#[test]
+fn test_find_id_pairs_with_k_differences() {
+    let ids = vec![
+        "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
+    ];
+    assert_eq!(
+        find_id_pairs_with_k_differences(&ids, 2),
+        vec![("abcde", "axcye")]
+    );
+    assert_eq!(
+        find_id_pairs_with_k_differences(&ids, 0),
+        Vec::<(&str, &str)>::new()
+    );
+    assert_eq!(
+        find_id_pairs_with_k_differences(&["ab", "ba", "ab"], 0),
+        vec![("ab", "ab")]
+    );
+}
+
+/// Times `find_id_pairs` on growing numbers of random IDs and on
+/// growing ID lengths, to show that the time taken grows linearly
+/// with each, and checks it against `find_id_pair` on a smaller list.
+/// Run it with `cargo test --release --bin day02 -- --ignored
+/// --nocapture`.
+#[test]
+#[ignore]
+fn bench_find_id_pairs() {
+    use rand::{Rng, SeedableRng};
+    use std::time::Instant;
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20181202);
+    // Makes `count` random IDs of `len` letters, where the last is the
+    // middle one with a letter changed.
+    let mut make_ids = |count: usize, len: usize| -> Vec<String> {
+        let mut ids: Vec<String> = (0..count)
+            .map(|_| {
+                (0..len)
+                    .map(|_| char::from(rng.gen_range(b'a'..=b'z')))
+                    .collect()
+            })
+            .collect();
+        let mut twin: Vec<char> = ids[count / 2].chars().collect();
+        twin[len / 2] = if twin[len / 2] == 'z' { 'a' } else { 'z' };
+        ids[count - 1] = twin.into_iter().collect();
+        ids
+    };
+    let time = |owned: &[String]| {
+        let ids: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+        let start = Instant::now();
+        let pairs = find_id_pairs(&ids);
+        let elapsed = start.elapsed();
+        assert!(pairs.contains(&(ids[ids.len() / 2], ids[ids.len() - 1])));
+        println!(
+            "{} IDs of length {}: {elapsed:?} ({:?} per letter)",
+            ids.len(),
+            ids[0].len(),
+            elapsed / (ids.len() * ids[0].len()) as u32
+        );
+    };
+    for count in [25_000, 50_000, 100_000, 200_000] {
+        time(&make_ids(count, 26));
+    }
+    for len in [13, 26, 52, 104] {
+        time(&make_ids(50_000, len));
+    }
+
+    let owned = make_ids(5_000, 26);
+    let ids: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+    let quadratic = find_id_pair(&ids).expect("a pair was planted");
+    assert_eq!(find_id_pairs(&ids).first(), Some(&quadratic));
+}
+
 fn solve_part2(ids: &[&str]) -> Result<String, Fail> {
-    match find_id_pair(ids) {
+    // If there are several pairs, use the one involving the earliest
+    // ID, as the quadratic search did.
+    match find_id_pairs(ids).first() {
         None => Err(Fail("no suitable pair".to_string())),
-        Some((left, right)) => {
-            let rest: String = left
-                .chars()
-                .zip(right.chars())
-                .filter_map(|(l, r)| if l == r { Some(r) } else { None })
-                .collect();
-            Ok(rest)
-        }
+        Some((left, right)) => Ok(common_letters(&[left, right])?),
     }
 }
 
@@ -126,8 +439,70 @@ fn test_solve_part2() {
     }
 }
 
+#[test]
+fn test_solve_part2_several_pairs() {
+    // "xbc" matches "abc" too, but "abc" and "abd" come first.
+    let ids = vec!["xbc", "abd", "abc"];
+    assert_eq!(solve_part2(&ids).expect("there are pairs"), "bc");
+    let ids = vec!["abd", "xbc", "abc"];
+    assert_eq!(solve_part2(&ids).expect("there are pairs"), "ab");
+}
+
+fn parse_options() -> Result<ChecksumOptions, Fail> {
+    let m = Command::new("Advent of code 2018 day 2")
+        .about("Solves the day 2 puzzle and optionally analyses letter frequencies")
+        .arg(
+            Arg::new("frequencies")
+                .long("frequencies")
+                .takes_value(true)
+                .help("comma-separated letter frequencies to analyse, for example 2,3,4"),
+        )
+        .arg(
+            Arg::new("fold-case")
+                .long("fold-case")
+                .help("treat upper and lower case letters as the same"),
+        )
+        .arg(
+            Arg::new("graphemes")
+                .long("graphemes")
+                .help("count grapheme clusters instead of chars"),
+        )
+        .get_matches();
+    let mut options = ChecksumOptions {
+        fold_case: m.is_present("fold-case"),
+        counting: if m.is_present("graphemes") {
+            Counting::Graphemes
+        } else {
+            Counting::Chars
+        },
+        ..ChecksumOptions::default()
+    };
+    if let Some(freqs) = m.value_of("frequencies") {
+        options.frequencies = freqs
+            .split(',')
+            .map(|f| {
+                f.trim()
+                    .parse()
+                    .map_err(|e| Fail(format!("invalid frequency '{f}': {e}")))
+            })
+            .collect::<Result<BTreeSet<usize>, Fail>>()?;
+    }
+    Ok(options)
+}
+
 fn main() {
+    let options = parse_options().expect("valid command-line options");
     let ids = get_ids(str::from_utf8(include_bytes!("input.txt")).expect("valid input file"));
     println!("Day 02 part 1: {}", checksum(&ids));
+    if options != ChecksumOptions::default() {
+        let result = frequency_checksum(&ids, &options);
+        for (freq, count) in result.by_frequency.iter() {
+            println!("IDs having a letter which occurs exactly {freq} times: {count}");
+        }
+        match result.product() {
+            Some(product) => println!("Checksum for these frequencies: {product}"),
+            None => println!("Checksum for these frequencies overflows"),
+        }
+    }
     println!("Day 02 part 2: {}", solve_part2(&ids).expect("solution"));
 }
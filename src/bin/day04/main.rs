@@ -1,27 +1,177 @@
-use std::collections::BTreeMap;
+use std::cmp::max;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
 use std::hash::Hash;
+use std::path::Path;
 use std::str;
 
+use clap::{Arg, Command};
+use itertools::Itertools;
 use sscanf::scanf;
 
 use lib::error::Fail;
+use lib::input::read_file_as_string;
 
 #[derive(Debug, Hash, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 struct Guard(u32);
 
+impl Display for Guard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 #[derive(Debug, Hash, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 struct Minutes(u32);
 
+impl Display for Minutes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl Minutes {
     fn checked_add(&self, n: Minutes) -> Option<Minutes> {
         self.0.checked_add(n.0).map(Minutes)
     }
-    fn checked_sub(&self, n: Minutes) -> Option<Minutes> {
-        self.0.checked_sub(n.0).map(Minutes)
+}
+
+/// The year which all the records come from.
+const YEAR: u32 = 1518;
+
+/// A point in time, to the nearest minute.  The derived ordering is
+/// chronological.
+#[derive(Debug, Hash, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
+struct Timestamp {
+    year: u32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+}
+
+fn is_leap_year(year: u32) -> bool {
+    (year.is_multiple_of(4) && !year.is_multiple_of(100)) || year.is_multiple_of(400)
+}
+
+fn days_in_month(year: u32, month: u32) -> Option<u32> {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => Some(31),
+        4 | 6 | 9 | 11 => Some(30),
+        2 if is_leap_year(year) => Some(29),
+        2 => Some(28),
+        _ => None,
+    }
+}
+
+impl Timestamp {
+    fn new(year: u32, month: u32, day: u32, hour: u32, minute: u32) -> Result<Timestamp, Fail> {
+        match days_in_month(year, month) {
+            None => Err(Fail(format!("month {month} is out of range"))),
+            Some(days) if day < 1 || day > days => Err(Fail(format!(
+                "day {day} is out of range for {year}-{month:02}"
+            ))),
+            Some(_) if hour > 23 => Err(Fail(format!("hour {hour} is out of range"))),
+            Some(_) if minute > 59 => Err(Fail(format!("minute {minute} is out of range"))),
+            Some(_) => Ok(Timestamp {
+                year,
+                month,
+                day,
+                hour,
+                minute,
+            }),
+        }
+    }
+
+    /// Returns the number of minutes since 0000-03-01 00:00 in the
+    /// proleptic Gregorian calendar.
+    fn minutes_since_epoch(&self) -> i64 {
+        // Count years from March, so that the leap day comes last.
+        let (year, month) = if self.month > 2 {
+            (i64::from(self.year), i64::from(self.month) - 3)
+        } else {
+            (i64::from(self.year) - 1, i64::from(self.month) + 9)
+        };
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        let days = year * 365 + year.div_euclid(4) - year.div_euclid(100)
+            + year.div_euclid(400)
+            + day_of_year;
+        (days * 24 + i64::from(self.hour)) * 60 + i64::from(self.minute)
+    }
+
+    /// Returns the number of minutes from `earlier` to `self`, or
+    /// `None` if `earlier` is later.
+    fn minutes_since(&self, earlier: &Timestamp) -> Option<Minutes> {
+        u32::try_from(self.minutes_since_epoch() - earlier.minutes_since_epoch())
+            .ok()
+            .map(Minutes)
+    }
+}
+
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute
+        )
     }
 }
 
+#[test]
+fn test_timestamp_validation() {
+    assert!(Timestamp::new(1518, 11, 1, 0, 0).is_ok());
+    assert!(Timestamp::new(1518, 12, 31, 23, 59).is_ok());
+    assert!(Timestamp::new(1518, 0, 1, 0, 0).is_err());
+    assert!(Timestamp::new(1518, 13, 1, 0, 0).is_err());
+    assert!(Timestamp::new(1518, 11, 0, 0, 0).is_err());
+    assert!(Timestamp::new(1518, 11, 31, 0, 0).is_err());
+    assert!(Timestamp::new(1518, 11, 1, 24, 0).is_err());
+    assert!(Timestamp::new(1518, 11, 1, 0, 60).is_err());
+    // 1518 was not a leap year.
+    assert!(Timestamp::new(1518, 2, 29, 0, 0).is_err());
+    assert!(Timestamp::new(1520, 2, 29, 0, 0).is_ok());
+}
+
+#[test]
+fn test_parse_event_rejects_other_years() {
+    assert!(parse_event("[1518-11-01 00:00] wakes up", 1).is_ok());
+    match parse_event("[1519-11-01 00:00] wakes up", 3) {
+        Err(Fail(msg)) => assert_eq!(
+            msg,
+            "line number 3 '[1519-11-01 00:00] wakes up' has an invalid timestamp: year 1519 is not 1518"
+        ),
+        Ok(event) => panic!("expected an error, got {event:?}"),
+    }
+}
+
+#[test]
+fn test_minutes_since() {
+    let t = |month, day, hour, minute| {
+        Timestamp::new(YEAR, month, day, hour, minute).expect("valid timestamp")
+    };
+    assert_eq!(
+        t(11, 1, 0, 25).minutes_since(&t(11, 1, 0, 5)),
+        Some(Minutes(20))
+    );
+    assert_eq!(
+        t(11, 2, 0, 2).minutes_since(&t(11, 1, 23, 58)),
+        Some(Minutes(4))
+    );
+    assert_eq!(
+        t(3, 1, 0, 0).minutes_since(&t(2, 28, 23, 59)),
+        Some(Minutes(1))
+    );
+    assert_eq!(t(1, 1, 0, 0).minutes_since(&t(12, 31, 23, 0)), None);
+    assert_eq!(
+        Timestamp::new(1519, 1, 1, 0, 0)
+            .expect("valid timestamp")
+            .minutes_since(&t(12, 31, 23, 0)),
+        Some(Minutes(60))
+    );
+}
+
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 enum EventType {
     ShiftStart(Guard),
@@ -31,21 +181,52 @@ enum EventType {
 
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq)]
 struct Event {
+    when: Timestamp,
     kind: EventType,
-    minute: Minutes,
+    /// Where the event appears in the input, counting from 1.
+    line_number: usize,
 }
 
 #[derive(Debug, Clone)]
 struct EventHistory {
     current_guard: Option<Guard>,
-    asleep_since: Option<Minutes>,
+    asleep_since: Option<Timestamp>,
+    /// The time of the most recent event.
+    latest: Option<Timestamp>,
     sleeps: HashMap<Guard, Minutes>,
     asleep_during_minute: HashMap<(Guard, Minutes), usize>,
+    shifts: HashMap<Guard, usize>,
+    longest_nap: HashMap<Guard, Minutes>,
+}
+
+/// Shows the event as it appears in the input, give or take leading
+/// zeroes.
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] ", self.when)?;
+        match self.kind {
+            EventType::ShiftStart(guard) => write!(f, "Guard {guard} begins shift"),
+            EventType::FallsAsleep => f.write_str("falls asleep"),
+            EventType::Wakes => f.write_str("wakes up"),
+        }
+    }
 }
 
 fn parse_event(line: &str, line_number: usize) -> Result<Event, Fail> {
     match scanf!(line, "[{u32}-{u32}-{u32} {u32}:{u32}] {str}") {
-        Ok((_year, _month, _day, _hour, minute, text)) => {
+        Ok((year, month, day, hour, minute, text)) => {
+            // Timestamps work for any year, but the records all come
+            // from YEAR, so anything else is a mistake in the input.
+            if year != YEAR {
+                return Err(Fail(format!(
+                    "line number {line_number} '{line}' has an invalid timestamp: year {year} is not {YEAR}"
+                )));
+            }
+            let when = Timestamp::new(year, month, day, hour, minute).map_err(|e| {
+                Fail(format!(
+                    "line number {line_number} '{line}' has an invalid timestamp: {e}"
+                ))
+            })?;
             let kind = if text == "falls asleep" {
                 EventType::FallsAsleep
             } else if text == "wakes up" {
@@ -55,14 +236,15 @@ fn parse_event(line: &str, line_number: usize) -> Result<Event, Fail> {
                     Ok(guard) => EventType::ShiftStart(Guard(guard)),
                     Err(e) => {
                         return Err(Fail(format!(
-                            "text '{text}' on line {line_number} has wrong format: {e}"
+                            "line number {line_number} '{line}' has wrong format: {e}"
                         )));
                     }
                 }
             };
             Ok(Event {
+                when,
                 kind,
-                minute: Minutes(minute),
+                line_number,
             })
         }
         Err(e) => Err(Fail(format!(
@@ -76,159 +258,354 @@ impl EventHistory {
         EventHistory {
             current_guard: None,
             asleep_since: None,
+            latest: None,
             sleeps: HashMap::new(),
             asleep_during_minute: HashMap::new(),
+            shifts: HashMap::new(),
+            longest_nap: HashMap::new(),
         }
     }
 
-    fn biggest_sleeper(&self) -> Result<Guard, Fail> {
-        let inverted = invert_map(&self.sleeps);
-        match inverted.into_iter().rev().next() {
-            Some((_minutes, guard)) => Ok(guard),
-            None => Err(Fail("there were no events at all".to_string())),
-        }
+    fn biggest_sleeper(&self, policy: TiePolicy) -> Result<Guard, Fail> {
+        let most = match self.sleeps.values().max() {
+            Some(most) => *most,
+            None => {
+                return Err(Fail("there were no events at all".to_string()));
+            }
+        };
+        let candidates: Vec<Guard> = self
+            .sleeps
+            .iter()
+            .filter(|(_, total)| **total == most)
+            .map(|(guard, _)| *guard)
+            .sorted()
+            .collect();
+        break_tie(&candidates, policy).map_err(|tied| {
+            Fail(format!(
+                "guards {tied} tie for the most time asleep ({} minutes)",
+                most.0
+            ))
+        })
     }
 
-    fn minute_during_which_sleeps_most(&self, g: &Guard) -> Option<(Minutes, usize)> {
-        let mut best: Option<(Minutes, usize)> = None;
-        for ((_guard, minute), count) in self
+    fn minute_during_which_sleeps_most(
+        &self,
+        g: &Guard,
+        policy: TiePolicy,
+    ) -> Result<Option<(Minutes, usize)>, Fail> {
+        let highest = match self
             .asleep_during_minute
             .iter()
             .filter(|((guard, _minute), _count)| *guard == *g)
+            .map(|(_, count)| *count)
+            .max()
         {
-            if let Some((_, highest_count)) = best {
-                if highest_count <= *count {
-                    best = Some((*minute, *count));
-                }
-            } else {
-                best = Some((*minute, *count));
+            Some(highest) => highest,
+            None => {
+                return Ok(None);
             }
+        };
+        let candidates: Vec<Minutes> = self
+            .asleep_during_minute
+            .iter()
+            .filter(|((guard, _minute), count)| *guard == *g && **count == highest)
+            .map(|((_guard, minute), _count)| *minute)
+            .sorted()
+            .collect();
+        break_tie(&candidates, policy)
+            .map(|minute| Some((minute, highest)))
+            .map_err(|tied| {
+                Fail(format!(
+                    "guard {g} was asleep {highest} times during each of minutes {tied}"
+                ))
+            })
+    }
+}
+
+/// What to do when more than one candidate answer is equally good.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TiePolicy {
+    Reject,
+    Lowest,
+}
+
+/// Chooses between the (sorted, non-empty) `candidates` according
+/// to `policy`.  If there is a tie which the policy rejects, the
+/// error describes the tied candidates.
+fn break_tie<T: Copy + Display>(candidates: &[T], policy: TiePolicy) -> Result<T, String> {
+    match (candidates, policy) {
+        ([only], _) | ([only, ..], TiePolicy::Lowest) => Ok(*only),
+        _ => Err(candidates.iter().join(", ")),
+    }
+}
+
+const MINUTES_PER_HOUR: usize = 60;
+
+/// Sleep statistics for one guard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GuardReport {
+    guard: Guard,
+    total_sleep: Minutes,
+    shifts: usize,
+    /// The number of times the guard was asleep during each minute
+    /// past the hour.
+    histogram: [usize; MINUTES_PER_HOUR],
+    longest_nap: Minutes,
+    /// The minutes during which the guard was most often asleep (all
+    /// of them, if there is a tie), and how often that was.  Empty if
+    /// the guard never slept.
+    sleepiest_minutes: (Vec<Minutes>, usize),
+}
+
+impl EventHistory {
+    fn report(&self) -> Vec<GuardReport> {
+        let guards: BTreeSet<Guard> = self.shifts.keys().copied().collect();
+        guards
+            .into_iter()
+            .map(|guard| {
+                let mut histogram = [0; MINUTES_PER_HOUR];
+                for (minute, count) in histogram.iter_mut().enumerate() {
+                    if let Some(n) = self
+                        .asleep_during_minute
+                        .get(&(guard, Minutes(minute as u32)))
+                    {
+                        *count = *n;
+                    }
+                }
+                let highest = histogram.iter().copied().max().unwrap_or(0);
+                let sleepiest: Vec<Minutes> = if highest == 0 {
+                    Vec::new()
+                } else {
+                    (0..MINUTES_PER_HOUR)
+                        .filter(|m| histogram[*m] == highest)
+                        .map(|m| Minutes(m as u32))
+                        .collect()
+                };
+                GuardReport {
+                    guard,
+                    total_sleep: self.sleeps.get(&guard).copied().unwrap_or(Minutes(0)),
+                    shifts: self.shifts.get(&guard).copied().unwrap_or(0),
+                    histogram,
+                    longest_nap: self.longest_nap.get(&guard).copied().unwrap_or(Minutes(0)),
+                    sleepiest_minutes: (sleepiest, highest),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Formats the per-minute histograms as CSV, one row per guard.
+fn histogram_csv(reports: &[GuardReport]) -> String {
+    let mut csv = String::from("guard");
+    for minute in 0..MINUTES_PER_HOUR {
+        csv.push_str(&format!(",{minute}"));
+    }
+    csv.push('\n');
+    for report in reports.iter() {
+        csv.push_str(&report.guard.0.to_string());
+        for count in report.histogram.iter() {
+            csv.push_str(&format!(",{count}"));
         }
-        best
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Draws the per-minute histograms in the style of the puzzle's
+/// timeline, one row per guard.  Minutes during which the guard was
+/// never asleep are shown as '.', and darker characters show minutes
+/// during which the guard was more often asleep.
+fn heatmap(reports: &[GuardReport]) -> String {
+    const SHADES: &[u8] = b":-=+*#%@";
+    let highest = reports
+        .iter()
+        .flat_map(|r| r.histogram.iter().copied())
+        .max()
+        .unwrap_or(0);
+    let width = reports
+        .iter()
+        .map(|r| r.guard.0.to_string().len() + 1)
+        .max()
+        .unwrap_or(0)
+        .max(2);
+    let mut out = String::new();
+    out.push_str(&format!("{:width$}  Minute\n", "ID"));
+    let tens: String = (0..MINUTES_PER_HOUR)
+        .map(|m| char::from(b'0' + (m / 10) as u8))
+        .collect();
+    let units: String = (0..MINUTES_PER_HOUR)
+        .map(|m| char::from(b'0' + (m % 10) as u8))
+        .collect();
+    out.push_str(&format!("{:width$}  {tens}\n", ""));
+    out.push_str(&format!("{:width$}  {units}\n", ""));
+    for report in reports.iter() {
+        let row: String = report
+            .histogram
+            .iter()
+            .map(|&count| {
+                if count == 0 {
+                    '.'
+                } else {
+                    // Scale non-zero counts onto the shades, so that
+                    // only the highest count gets the darkest one.
+                    let shade = (count * SHADES.len() - 1) / highest;
+                    char::from(SHADES[shade])
+                }
+            })
+            .collect();
+        let id = format!("#{}", report.guard.0);
+        out.push_str(&format!("{id:width$}  {row}\n"));
     }
+    out
 }
 
 impl TryFrom<&[Event]> for EventHistory {
     type Error = Fail;
     fn try_from(events: &[Event]) -> Result<EventHistory, Fail> {
-        events.iter().try_fold(EventHistory::new(), update_history)
+        let mut history = EventHistory::new();
+        for event in events.iter() {
+            history.record(event)?;
+        }
+        Ok(history)
     }
 }
 
-fn update_history(mut history: EventHistory, event: &Event) -> Result<EventHistory, Fail> {
-    //dbg!(&history);
-    //dbg!(&event);
-    match event.kind {
-        EventType::ShiftStart(guard) => {
-            if history.asleep_since.is_some() {
-                Err(Fail(
-                    "next shift begins but previous guard is still asleep".to_string(),
-                ))
-            } else {
-                Ok(EventHistory {
-                    current_guard: Some(guard),
-                    asleep_since: None,
-                    sleeps: history.sleeps,
-                    asleep_during_minute: history.asleep_during_minute,
-                })
+impl EventHistory {
+    /// Updates the history with the next event.  Events must be
+    /// recorded in chronological order.  If the event is rejected,
+    /// the history is left unchanged.
+    fn record(&mut self, event: &Event) -> Result<(), Fail> {
+        let fail = |why: &str| {
+            Fail(format!(
+                "line number {} '{event}': {why}",
+                event.line_number
+            ))
+        };
+        if let Some(previous) = self.latest {
+            if event.when < previous {
+                return Err(fail(&format!(
+                    "event at {} is earlier than the previous event at {previous}",
+                    event.when
+                )));
             }
         }
-        EventType::Wakes => match (history.current_guard, history.asleep_since) {
-            (Some(guard), Some(begin)) => match event.minute.checked_sub(begin) {
-                None => Err(Fail("underflow in time subtraction".to_string())),
-                Some(n) => {
-                    for m in (begin.0)..(event.minute.0) {
-                        history
-                            .asleep_during_minute
-                            .entry((guard, Minutes(m)))
-                            .and_modify(|n| *n += 1)
-                            .or_insert(1);
-                    }
-
-                    let tot = history.sleeps.get(&guard).copied().unwrap_or(Minutes(0));
+        match event.kind {
+            EventType::ShiftStart(guard) => {
+                if self.asleep_since.is_some() {
+                    return Err(fail("next shift begins but previous guard is still asleep"));
+                }
+                *self.shifts.entry(guard).or_insert(0) += 1;
+                self.current_guard = Some(guard);
+            }
+            EventType::Wakes => match (self.current_guard, self.asleep_since) {
+                (Some(guard), Some(begin)) => {
+                    let n = match event.when.minutes_since(&begin) {
+                        None => {
+                            return Err(fail("underflow in time subtraction"));
+                        }
+                        Some(n) => n,
+                    };
+                    let tot = self.sleeps.get(&guard).copied().unwrap_or(Minutes(0));
                     match tot.checked_add(n) {
                         Some(tot) => {
-                            history.sleeps.insert(guard, tot);
-                            Ok(EventHistory {
-                                current_guard: Some(guard),
-                                asleep_since: None,
-                                sleeps: history.sleeps,
-                                asleep_during_minute: history.asleep_during_minute,
-                            })
+                            self.sleeps.insert(guard, tot);
                         }
-                        None => Err(Fail("overflow in time addition".to_string())),
+                        None => {
+                            return Err(fail("overflow in time addition"));
+                        }
+                    }
+                    // Naps may cross the hour, so we count the minute
+                    // past the hour of each minute asleep.
+                    let start = begin.minutes_since_epoch();
+                    for m in start..(start + i64::from(n.0)) {
+                        let minute = Minutes(m.rem_euclid(60) as u32);
+                        self.asleep_during_minute
+                            .entry((guard, minute))
+                            .and_modify(|n| *n += 1)
+                            .or_insert(1);
                     }
+                    self.longest_nap
+                        .entry(guard)
+                        .and_modify(|longest| *longest = max(*longest, n))
+                        .or_insert(n);
+                    self.asleep_since = None;
+                }
+                (_, None) => {
+                    return Err(fail(
+                        "input lines out of order (guard woke but was not asleep)",
+                    ));
+                }
+                (None, _) => {
+                    return Err(fail(
+                        "input lines out of order (guard was asleep but was not on duty)",
+                    ));
                 }
             },
-            (_, None) => Err(Fail(
-                "input lines out of order (guard woke but was not asleep)".to_string(),
-            )),
-            (None, _) => Err(Fail(
-                "input lines out of order (guard was asleep but was not on duty)".to_string(),
-            )),
-        },
-        EventType::FallsAsleep => {
-            if history.asleep_since.is_some() {
-                Err(Fail(
-                    "fell asleep twice without intervening wake-up".to_string(),
-                ))
-            } else {
-                history.asleep_since = Some(event.minute);
-                Ok(history)
+            EventType::FallsAsleep => {
+                if self.asleep_since.is_some() {
+                    return Err(fail("fell asleep twice without intervening wake-up"));
+                }
+                self.asleep_since = Some(event.when);
             }
         }
+        self.latest = Some(event.when);
+        Ok(())
     }
-}
-
-fn invert_map<K, V>(input: &HashMap<K, V>) -> BTreeMap<V, K>
-where
-    K: Copy + Hash + Ord + PartialOrd,
-    V: Copy + Ord + PartialOrd,
-{
-    input.iter().map(|(k, v)| (*v, *k)).collect()
-}
 
-fn solve_part1(history: &EventHistory) -> u32 {
-    match history.biggest_sleeper() {
-        Ok(guard) => {
-            let (when, _) = history
-                .minute_during_which_sleeps_most(&guard)
-                .expect("oops");
-            when.0 * guard.0
+    /// Adds the sleep records of `other`, which was built from a
+    /// separate log, into this history.  `other` must not end with a
+    /// guard still asleep, since we don't know when that nap ends.
+    fn merge(&mut self, other: &EventHistory) -> Result<(), Fail> {
+        if other.asleep_since.is_some() {
+            return Err(Fail(
+                "cannot merge a history which ends with a guard asleep".to_string(),
+            ));
         }
-        Err(e) => {
-            panic!("{e}");
+        let mut sleeps = self.sleeps.clone();
+        for (guard, n) in other.sleeps.iter() {
+            let tot = sleeps.get(guard).copied().unwrap_or(Minutes(0));
+            match tot.checked_add(*n) {
+                Some(tot) => {
+                    sleeps.insert(*guard, tot);
+                }
+                None => {
+                    return Err(Fail("overflow in time addition".to_string()));
+                }
+            }
+        }
+        self.sleeps = sleeps;
+        for (key, count) in other.asleep_during_minute.iter() {
+            *self.asleep_during_minute.entry(*key).or_insert(0) += count;
         }
+        for (guard, count) in other.shifts.iter() {
+            *self.shifts.entry(*guard).or_insert(0) += count;
+        }
+        for (guard, n) in other.longest_nap.iter() {
+            self.longest_nap
+                .entry(*guard)
+                .and_modify(|longest| *longest = max(*longest, *n))
+                .or_insert(*n);
+        }
+        Ok(())
+    }
+}
+
+fn solve_part1(history: &EventHistory, policy: TiePolicy) -> Result<u32, Fail> {
+    let guard = history.biggest_sleeper(policy)?;
+    match history.minute_during_which_sleeps_most(&guard, policy)? {
+        Some((when, _)) => Ok(when.0 * guard.0),
+        None => Err(Fail(format!("guard {guard} never slept"))),
     }
 }
 
 #[test]
 fn test_biggest_sleeper() {
-    const EXAMPLE: &str = concat!(
-        "[1518-11-01 00:00] Guard #10 begins shift\n",
-        "[1518-11-01 00:05] falls asleep\n",
-        "[1518-11-01 00:25] wakes up\n",
-        "[1518-11-01 00:30] falls asleep\n",
-        "[1518-11-01 00:55] wakes up\n",
-        "[1518-11-01 23:58] Guard #99 begins shift\n",
-        "[1518-11-02 00:40] falls asleep\n",
-        "[1518-11-02 00:50] wakes up\n",
-        "[1518-11-03 00:05] Guard #10 begins shift\n",
-        "[1518-11-03 00:24] falls asleep\n",
-        "[1518-11-03 00:29] wakes up\n",
-        "[1518-11-04 00:02] Guard #99 begins shift\n",
-        "[1518-11-04 00:36] falls asleep\n",
-        "[1518-11-04 00:46] wakes up\n",
-        "[1518-11-05 00:03] Guard #99 begins shift\n",
-        "[1518-11-05 00:45] falls asleep\n",
-        "[1518-11-05 00:55] wakes up    \n"
-    );
     let events = parse_events(EXAMPLE).expect("test input format should be correct");
     let history: EventHistory = EventHistory::try_from(events.as_slice()).unwrap();
-    match history.biggest_sleeper() {
+    match history.biggest_sleeper(TiePolicy::Reject) {
         Ok(guard) => {
             assert_eq!(guard, Guard(10));
-            assert_eq!(solve_part1(&history), 240);
+            assert_eq!(solve_part1(&history, TiePolicy::Reject).ok(), Some(240));
         }
         Err(e) => {
             panic!("biggest_sleeper failed: {e}");
@@ -237,23 +614,362 @@ fn test_biggest_sleeper() {
 }
 
 fn parse_events(s: &str) -> Result<Vec<Event>, Fail> {
-    let mut lines: Vec<&str> = s.split_terminator('\n').collect();
-    lines.sort();
-    lines
-        .into_iter()
+    let mut events = s
+        .split_terminator('\n')
         .map(|s| s.trim())
         .enumerate()
-        .map(|(n, line)| parse_event(line, n))
-        .collect::<Result<Vec<Event>, _>>()
+        .map(|(n, line)| parse_event(line, n + 1))
+        .collect::<Result<Vec<Event>, _>>()?;
+    // The sort is stable, so simultaneous events stay in file order.
+    events.sort_by_key(|event| event.when);
+    Ok(events)
+}
+
+#[test]
+fn test_parse_events_sorts_chronologically() {
+    // Sorting the text would put the shift start last, because
+    // "1-04" sorts after "01-05", and the last nap's end before its
+    // start, because "10:00" sorts before "9:00".
+    let events = parse_events(concat!(
+        "[1518-01-05 00:10] wakes up\n",
+        "[1518-1-04 23:58] Guard #7 begins shift\n",
+        "[1518-01-05 00:05] falls asleep\n",
+        "[1518-01-05 10:00] wakes up\n",
+        "[1518-01-05 9:00] falls asleep\n",
+    ))
+    .expect("valid input");
+    let kinds: Vec<(EventType, usize)> = events.iter().map(|e| (e.kind, e.line_number)).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (EventType::ShiftStart(Guard(7)), 2),
+            (EventType::FallsAsleep, 3),
+            (EventType::Wakes, 1),
+            (EventType::FallsAsleep, 5),
+            (EventType::Wakes, 4),
+        ]
+    );
+    assert!(parse_events("[1518-02-29 00:00] wakes up\n").is_err());
+}
+
+#[test]
+fn test_errors_name_the_original_line() {
+    let failure = |input: &str| -> String {
+        match parse_events(input).and_then(|events| EventHistory::try_from(events.as_slice())) {
+            Ok(_) => panic!("expected input to be rejected: {input}"),
+            Err(Fail(msg)) => msg,
+        }
+    };
+    assert_eq!(
+        failure(concat!(
+            "[1518-01-05 00:10] falls asleep\n",
+            "[1518-01-04 23:58] Guard #7 begins shift\n",
+            "[1518-01-05 00:05] falls asleep\n",
+        )),
+        "line number 1 '[1518-01-05 00:10] falls asleep': fell asleep twice without intervening wake-up"
+    );
+    assert_eq!(
+        failure(concat!(
+            "[1518-01-05 00:10] wakes up\n",
+            "[1518-01-04 23:58] Guard #7 begins shift\n",
+        )),
+        "line number 1 '[1518-01-05 00:10] wakes up': input lines out of order (guard woke but was not asleep)"
+    );
+    assert!(failure(concat!(
+        "[1518-01-05 00:10] wakes up\n",
+        "[1518-01-04 23:58] Guard #7 starts shift\n",
+    ))
+    .starts_with("line number 2 '[1518-01-04 23:58] Guard #7 starts shift' has wrong format"));
+}
+
+#[test]
+fn test_nap_crossing_the_hour() {
+    let events = parse_events(concat!(
+        "[1518-01-04 23:58] Guard #7 begins shift\n",
+        "[1518-01-04 23:59] falls asleep\n",
+        "[1518-01-05 00:02] wakes up\n",
+    ))
+    .expect("valid input");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    assert_eq!(history.sleeps.get(&Guard(7)), Some(&Minutes(3)));
+    assert_eq!(
+        history.asleep_during_minute.get(&(Guard(7), Minutes(59))),
+        Some(&1)
+    );
+    assert_eq!(
+        history.asleep_during_minute.get(&(Guard(7), Minutes(1))),
+        Some(&1)
+    );
+    assert_eq!(
+        history.asleep_during_minute.get(&(Guard(7), Minutes(2))),
+        None
+    );
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = concat!(
+    "[1518-11-01 00:00] Guard #10 begins shift\n",
+    "[1518-11-01 00:05] falls asleep\n",
+    "[1518-11-01 00:25] wakes up\n",
+    "[1518-11-01 00:30] falls asleep\n",
+    "[1518-11-01 00:55] wakes up\n",
+    "[1518-11-01 23:58] Guard #99 begins shift\n",
+    "[1518-11-02 00:40] falls asleep\n",
+    "[1518-11-02 00:50] wakes up\n",
+    "[1518-11-03 00:05] Guard #10 begins shift\n",
+    "[1518-11-03 00:24] falls asleep\n",
+    "[1518-11-03 00:29] wakes up\n",
+    "[1518-11-04 00:02] Guard #99 begins shift\n",
+    "[1518-11-04 00:36] falls asleep\n",
+    "[1518-11-04 00:46] wakes up\n",
+    "[1518-11-05 00:03] Guard #99 begins shift\n",
+    "[1518-11-05 00:45] falls asleep\n",
+    // The trailing spaces check that lines are trimmed.
+    "[1518-11-05 00:55] wakes up    \n"
+);
+
+#[test]
+fn test_report() {
+    let events = parse_events(EXAMPLE).expect("test input format should be correct");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    let report = history.report();
+    assert_eq!(report.len(), 2);
+
+    let ten = &report[0];
+    assert_eq!(ten.guard, Guard(10));
+    assert_eq!(ten.total_sleep, Minutes(50));
+    assert_eq!(ten.shifts, 2);
+    assert_eq!(ten.longest_nap, Minutes(25));
+    assert_eq!(ten.sleepiest_minutes, (vec![Minutes(24)], 2));
+    assert_eq!(ten.histogram.iter().sum::<usize>(), 50);
+
+    let ninety_nine = &report[1];
+    assert_eq!(ninety_nine.guard, Guard(99));
+    assert_eq!(ninety_nine.total_sleep, Minutes(30));
+    assert_eq!(ninety_nine.shifts, 3);
+    assert_eq!(ninety_nine.longest_nap, Minutes(10));
+    assert_eq!(ninety_nine.sleepiest_minutes, (vec![Minutes(45)], 3));
+}
+
+#[test]
+fn test_report_ties_and_idle_guards() {
+    let events = parse_events(concat!(
+        "[1518-11-01 00:00] Guard #10 begins shift\n",
+        "[1518-11-01 00:05] falls asleep\n",
+        "[1518-11-01 00:07] wakes up\n",
+        "[1518-11-02 00:00] Guard #11 begins shift\n",
+    ))
+    .expect("valid input");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    let report = history.report();
+    assert_eq!(
+        report[0].sleepiest_minutes,
+        (vec![Minutes(5), Minutes(6)], 1)
+    );
+    assert_eq!(report[1].guard, Guard(11));
+    assert_eq!(report[1].total_sleep, Minutes(0));
+    assert_eq!(report[1].sleepiest_minutes, (vec![], 0));
+}
+
+#[test]
+fn test_histogram_csv() {
+    let events = parse_events(EXAMPLE).expect("test input format should be correct");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    let csv = histogram_csv(&history.report());
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("guard,0,1,2,"));
+    assert!(lines[0].ends_with(",58,59"));
+    assert!(lines[1].starts_with("10,0,0,0,0,0,1,1,"));
+    assert_eq!(lines[2].split(',').count(), 61);
+}
+
+#[test]
+fn test_heatmap() {
+    let events = parse_events(EXAMPLE).expect("test input format should be correct");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    let map = heatmap(&history.report());
+    let lines: Vec<&str> = map.lines().collect();
+    assert_eq!(lines[0], "ID   Minute");
+    assert_eq!(
+        lines[1],
+        "     000000000011111111112222222222333333333344444444445555555555"
+    );
+    assert_eq!(
+        lines[2],
+        "     012345678901234567890123456789012345678901234567890123456789"
+    );
+    assert_eq!(
+        lines[3],
+        "#10  .....===================#====.=========================....."
+    );
+    assert_eq!(
+        lines[4],
+        "#99  ....................................====#####@####=====....."
+    );
+}
+
+#[test]
+fn test_incremental_history() {
+    let events = parse_events(EXAMPLE).expect("test input format should be correct");
+    let mut history = EventHistory::new();
+    for event in events[..5].iter() {
+        history.record(event).expect("valid event");
+    }
+    // Only guard #10 has been on duty so far.
+    assert_eq!(
+        history.biggest_sleeper(TiePolicy::Reject).ok(),
+        Some(Guard(10))
+    );
+    assert_eq!(history.sleeps.get(&Guard(10)), Some(&Minutes(45)));
+    for event in events[5..].iter() {
+        history.record(event).expect("valid event");
+    }
+    assert_eq!(solve_part1(&history, TiePolicy::Reject).ok(), Some(240));
+
+    // Rejected events leave the history unchanged.
+    let before = history.report();
+    assert!(history.record(&events[6]).is_err());
+    assert!(history.record(&events[2]).is_err());
+    assert_eq!(history.report(), before);
+}
+
+#[test]
+fn test_merge_histories() {
+    let events = parse_events(EXAMPLE).expect("test input format should be correct");
+    let whole = EventHistory::try_from(events.as_slice()).expect("valid history");
+    let mut first = EventHistory::try_from(&events[..8]).expect("valid history");
+    let second = EventHistory::try_from(&events[8..]).expect("valid history");
+    first.merge(&second).expect("should be able to merge");
+    assert_eq!(first.report(), whole.report());
+    assert_eq!(solve_part1(&first, TiePolicy::Reject).ok(), Some(240));
+
+    let unfinished = EventHistory::try_from(&events[..2]).expect("valid history");
+    assert!(first.merge(&unfinished).is_err());
+}
+
+#[test]
+fn test_tied_sleepers() {
+    let events = parse_events(concat!(
+        "[1518-11-01 00:00] Guard #99 begins shift\n",
+        "[1518-11-01 00:05] falls asleep\n",
+        "[1518-11-01 00:15] wakes up\n",
+        "[1518-11-02 00:00] Guard #10 begins shift\n",
+        "[1518-11-02 00:20] falls asleep\n",
+        "[1518-11-02 00:30] wakes up\n",
+    ))
+    .expect("valid input");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    match history.biggest_sleeper(TiePolicy::Reject) {
+        Err(Fail(msg)) => assert_eq!(
+            msg,
+            "guards #10, #99 tie for the most time asleep (10 minutes)"
+        ),
+        Ok(guard) => panic!("expected a tie, got {guard}"),
+    }
+    assert_eq!(
+        history.biggest_sleeper(TiePolicy::Lowest).ok(),
+        Some(Guard(10))
+    );
+    assert_eq!(solve_part1(&history, TiePolicy::Lowest).ok(), Some(200));
+}
+
+#[test]
+fn test_tied_minutes() {
+    let events = parse_events(concat!(
+        "[1518-11-01 00:00] Guard #10 begins shift\n",
+        "[1518-11-01 00:05] falls asleep\n",
+        "[1518-11-01 00:07] wakes up\n",
+        "[1518-11-01 00:40] falls asleep\n",
+        "[1518-11-01 00:41] wakes up\n",
+        "[1518-11-02 00:00] Guard #10 begins shift\n",
+        "[1518-11-02 00:06] falls asleep\n",
+        "[1518-11-02 00:07] wakes up\n",
+        "[1518-11-02 00:40] falls asleep\n",
+        "[1518-11-02 00:41] wakes up\n",
+    ))
+    .expect("valid input");
+    let history = EventHistory::try_from(events.as_slice()).expect("valid history");
+    match history.minute_during_which_sleeps_most(&Guard(10), TiePolicy::Reject) {
+        Err(Fail(msg)) => assert_eq!(
+            msg,
+            "guard #10 was asleep 2 times during each of minutes 6, 40"
+        ),
+        Ok(best) => panic!("expected a tie, got {best:?}"),
+    }
+    assert_eq!(
+        history
+            .minute_during_which_sleeps_most(&Guard(10), TiePolicy::Lowest)
+            .ok(),
+        Some(Some((Minutes(6), 2)))
+    );
+    assert_eq!(
+        history
+            .minute_during_which_sleeps_most(&Guard(11), TiePolicy::Reject)
+            .ok(),
+        Some(None)
+    );
+}
+
+fn history_from_file(path: &Path) -> Result<EventHistory, Fail> {
+    let text = read_file_as_string(path)?;
+    let events = parse_events(&text)?;
+    EventHistory::try_from(events.as_slice())
+        .map_err(|Fail(msg)| Fail(format!("{}: {msg}", path.display())))
 }
 
 fn main() {
+    let m = Command::new("Advent of code 2018 day 4")
+        .about("Solves the day 4 puzzle and optionally reports on each guard's sleep")
+        .arg(
+            Arg::new("extra_logs")
+                .multiple_occurrences(true)
+                .allow_invalid_utf8(true)
+                .help("further log files to combine with the puzzle input"),
+        )
+        .arg(
+            Arg::new("ties")
+                .long("ties")
+                .takes_value(true)
+                .possible_values(["reject", "lowest"])
+                .default_value("reject")
+                .help("whether to reject tied answers or choose the lowest"),
+        )
+        .arg(
+            Arg::new("heatmap")
+                .long("heatmap")
+                .help("show how often each guard is asleep during each minute"),
+        )
+        .arg(
+            Arg::new("csv")
+                .long("csv")
+                .takes_value(true)
+                .allow_invalid_utf8(true)
+                .help("write each guard's per-minute sleep counts to this CSV file"),
+        )
+        .get_matches();
     let input = str::from_utf8(include_bytes!("input.txt"))
         .expect("input file should be correctly encoded");
     let events = parse_events(input).expect("valid input");
-    let history = EventHistory::try_from(events.as_slice()).expect("should not fail");
+    let mut history = EventHistory::try_from(events.as_slice()).expect("should not fail");
+    if let Some(paths) = m.values_of_os("extra_logs") {
+        for path in paths {
+            let extra = history_from_file(Path::new(path)).expect("valid log file");
+            history.merge(&extra).expect("should be able to merge logs");
+        }
+    }
 
-    let solution = solve_part1(&history);
+    let policy = match m.value_of("ties") {
+        Some("lowest") => TiePolicy::Lowest,
+        _ => TiePolicy::Reject,
+    };
+    let solution = solve_part1(&history, policy).expect("part 1 should have a unique answer");
     println!("Day 04 part 1: {}", solution);
-    //dbg!(&events);
+    if m.is_present("heatmap") {
+        print!("{}", heatmap(&history.report()));
+    }
+    if let Some(path) = m.value_of_os("csv") {
+        std::fs::write(path, histogram_csv(&history.report()))
+            .expect("should be able to write CSV file");
+    }
 }
@@ -1,36 +1,222 @@
-use std::collections::BTreeSet;
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug, Display, Formatter};
 use std::str;
 
-fn invert_polarity(ch: char) -> char {
-    if ch.is_ascii_uppercase() {
-        ch.to_ascii_lowercase()
-    } else {
-        ch.to_ascii_uppercase()
+use clap::{Arg, Command};
+use itertools::Itertools;
+use serde::Serialize;
+
+use lib::error::Fail;
+
+/// Decides which units of a polymer react with each other.
+trait ReactionRule {
+    type Unit: Copy + Eq + Ord + Debug + Into<char>;
+
+    /// Converts the text of a polymer into units, rejecting anything
+    /// outside the rule's alphabet.
+    fn parse(&self, polymer: &str) -> Result<Vec<Self::Unit>, Fail>;
+
+    /// Returns true if `a` and `b` annihilate when adjacent.
+    fn reacts(&self, a: Self::Unit, b: Self::Unit) -> bool;
+
+    /// Returns the type of `unit`.  Units which react with each other
+    /// have the same type.
+    fn unit_type(&self, unit: Self::Unit) -> Self::Unit;
+}
+
+fn invalid_unit(unit: char, pos: usize) -> Fail {
+    Fail(format!(
+        "polymer contains invalid unit {unit:?} at position {pos}"
+    ))
+}
+
+/// The puzzle's rule: an ASCII letter reacts with the same letter in
+/// the opposite case.
+struct AsciiCasePairs;
+
+impl ReactionRule for AsciiCasePairs {
+    type Unit = u8;
+
+    fn parse(&self, polymer: &str) -> Result<Vec<u8>, Fail> {
+        match polymer
+            .char_indices()
+            .find(|(_, ch)| !ch.is_ascii_alphabetic())
+        {
+            Some((pos, ch)) => Err(invalid_unit(ch, pos)),
+            None => Ok(polymer.as_bytes().to_vec()),
+        }
+    }
+
+    fn reacts(&self, a: u8, b: u8) -> bool {
+        a != b && a.eq_ignore_ascii_case(&b)
+    }
+
+    fn unit_type(&self, unit: u8) -> u8 {
+        unit.to_ascii_lowercase()
+    }
+}
+
+#[test]
+fn test_ascii_case_pairs() {
+    let rule = AsciiCasePairs;
+    assert!(rule.reacts(b'a', b'A'));
+    assert!(rule.reacts(b'A', b'a'));
+    assert!(!rule.reacts(b'a', b'a'));
+    assert!(!rule.reacts(b'a', b'B'));
+    assert_eq!(rule.parse("aB").expect("valid polymer"), b"aB");
+    // Digits have no case, so inverting their case used to make them
+    // react with themselves.
+    assert!(rule.parse("a11").is_err());
+    assert!(rule.parse("aé").is_err());
+}
+
+/// A unit reacts with the other unit of its pair, as given in an
+/// explicit table.
+struct PairTable {
+    partners: BTreeMap<char, char>,
+}
+
+impl PairTable {
+    fn new(pairs: &[(char, char)]) -> Result<PairTable, Fail> {
+        let mut partners: BTreeMap<char, char> = BTreeMap::new();
+        for (a, b) in pairs.iter() {
+            if a == b {
+                return Err(Fail(format!("unit {a:?} cannot react with itself")));
+            }
+            for (unit, partner) in [(*a, *b), (*b, *a)] {
+                match partners.insert(unit, partner) {
+                    Some(previous) if previous != partner => {
+                        return Err(Fail(format!(
+                            "unit {unit:?} cannot react with both {previous:?} and {partner:?}"
+                        )));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(PairTable { partners })
     }
 }
 
-fn have_opposite_polarity(a: char, b: char) -> bool {
-    invert_polarity(a) == b
+impl ReactionRule for PairTable {
+    type Unit = char;
+
+    fn parse(&self, polymer: &str) -> Result<Vec<char>, Fail> {
+        polymer
+            .char_indices()
+            .map(|(pos, ch)| {
+                if self.partners.contains_key(&ch) {
+                    Ok(ch)
+                } else {
+                    Err(invalid_unit(ch, pos))
+                }
+            })
+            .collect()
+    }
+
+    fn reacts(&self, a: char, b: char) -> bool {
+        self.partners.get(&a) == Some(&b)
+    }
+
+    fn unit_type(&self, unit: char) -> char {
+        match self.partners.get(&unit) {
+            Some(partner) => min(unit, *partner),
+            None => unit,
+        }
+    }
 }
 
-fn react_once(letters: &[char]) -> (Vec<char>, bool) {
+#[test]
+fn test_pair_table() {
+    let rule = PairTable::new(&[('(', ')'), ('[', ']')]).expect("valid table");
+    assert!(rule.reacts('(', ')'));
+    assert!(rule.reacts(')', '('));
+    assert!(!rule.reacts('(', ']'));
+    assert_eq!(rule.unit_type(')'), '(');
+    assert!(rule.parse("([])").is_ok());
+    assert!(rule.parse("(a)").is_err());
+    assert!(PairTable::new(&[('(', ')'), ('(', ']')]).is_err());
+    assert!(PairTable::new(&[('(', ')'), ('a', 'a')]).is_err());
+    assert!(parse_pairs("()aa").is_err());
+}
+
+/// A letter reacts with a different letter which has the same
+/// lower-case form, for any letter which has case in Unicode.  This
+/// compares `char::to_lowercase`, which is not full Unicode case
+/// folding: for example 'ß' and 'ẞ' react, but 'ß' and "ss" could
+/// not, and final sigma is a letter of its own.
+struct UnicodeLowercase;
+
+impl ReactionRule for UnicodeLowercase {
+    type Unit = char;
+
+    fn parse(&self, polymer: &str) -> Result<Vec<char>, Fail> {
+        polymer
+            .char_indices()
+            .map(|(pos, ch)| {
+                if ch.is_lowercase() || ch.is_uppercase() {
+                    Ok(ch)
+                } else {
+                    Err(invalid_unit(ch, pos))
+                }
+            })
+            .collect()
+    }
+
+    fn reacts(&self, a: char, b: char) -> bool {
+        a != b && a.to_lowercase().eq(b.to_lowercase())
+    }
+
+    fn unit_type(&self, unit: char) -> char {
+        let mut lower = unit.to_lowercase();
+        match (lower.next(), lower.next()) {
+            (Some(ch), None) => ch,
+            // The lower-case form is not a single char, so the unit
+            // is its own type.
+            _ => unit,
+        }
+    }
+}
+
+#[test]
+fn test_unicode_lowercase() {
+    let rule = UnicodeLowercase;
+    assert!(rule.reacts('a', 'A'));
+    assert!(rule.reacts('Ж', 'ж'));
+    assert!(!rule.reacts('ж', 'ж'));
+    assert!(!rule.reacts('Ж', 'ш'));
+    assert_eq!(rule.unit_type('Ж'), 'ж');
+    // Lower-case comparison, not case folding.
+    assert!(rule.reacts('ẞ', 'ß'));
+    assert!(!rule.reacts('Σ', 'ς'));
+    assert!(rule.parse("ΣσЖж").is_ok());
+    assert!(rule.parse("a1").is_err());
+    assert_eq!(
+        react_fully(&rule, &rule.parse("aΣσbB").expect("valid polymer")),
+        vec!['a']
+    );
+}
+
+/// Performs one pass over the polymer, removing non-overlapping
+/// reacting pairs.  This is the original quadratic approach, which
+/// we keep only to check `react_fully` against.
+#[cfg(test)]
+fn react_once<R: ReactionRule>(rule: &R, letters: &[R::Unit]) -> (Vec<R::Unit>, bool) {
     let mut result = Vec::with_capacity(letters.len());
     let mut changed = false;
-    // Initialise `leftover` in case there are less than 2 chars in
+    // Initialise `leftover` in case there are less than 2 units in
     // `letters`.
-    let mut leftover: Option<char> = letters.iter().copied().next();
+    let mut leftover: Option<R::Unit> = letters.iter().copied().next();
     let mut skip = false;
     for pair in letters.windows(2) {
-        //dbg!(pair);
-        //dbg!(&skip);
         if skip {
             skip = false;
             leftover = Some(pair[1]);
             continue;
         }
         match pair {
-            [a, b] if have_opposite_polarity(*a, *b) => {
-                //println!("eating {pair:?}");
+            [a, b] if rule.reacts(*a, *b) => {
                 leftover = None;
                 changed = true;
                 // Make sure we don't consider b as the first letter
@@ -45,73 +231,234 @@ fn react_once(letters: &[char]) -> (Vec<char>, bool) {
         }
     }
     if let Some(ch) = leftover {
-        //println!("passing through the leftover {ch}");
         result.push(ch);
-    } else {
-        //println!("There is no leftover");
     }
     (result, changed)
 }
 
 #[test]
 fn test_react_once() {
-    assert_eq!(react_once(&['a']), (vec!['a'], false));
-    assert_eq!(react_once(&['a', 'a']), (vec!['a', 'a'], false));
-    assert_eq!(react_once(&['a', 'A']), (vec![], true));
-    assert_eq!(react_once(&['b', 'a', 'A']), (vec!['b'], true));
+    assert_eq!(react_once(&AsciiCasePairs, b"a"), (b"a".to_vec(), false));
+    assert_eq!(react_once(&AsciiCasePairs, b"aa"), (b"aa".to_vec(), false));
+    assert_eq!(react_once(&AsciiCasePairs, b"aA"), (vec![], true));
+    assert_eq!(react_once(&AsciiCasePairs, b"baA"), (b"b".to_vec(), true));
     assert_eq!(
-        react_once(&['b', 'a', 'A', 'B']),
-        (vec!['b', 'B'], true),
+        react_once(&AsciiCasePairs, b"baAB"),
+        (b"bB".to_vec(), true),
         "baAB should yield bB"
     );
-    assert_eq!(react_once(&['b', 'B', 'a', 'A']), (vec![], true));
+    assert_eq!(react_once(&AsciiCasePairs, b"bBaA"), (vec![], true));
 }
 
-fn react_fully(letters: &str) -> String {
-    let mut input: Vec<char> = letters.chars().collect();
+#[cfg(test)]
+fn react_fully_by_passes<R: ReactionRule>(rule: &R, letters: &[R::Unit]) -> Vec<R::Unit> {
+    let mut input: Vec<R::Unit> = letters.to_vec();
     loop {
-        let (output, changed) = react_once(&input);
+        let (output, changed) = react_once(rule, &input);
         if !changed {
-            return input.iter().collect();
+            return input;
         }
         assert_ne!(input, output);
         input = output;
     }
 }
 
+/// Reacts the polymer in a single pass.  Each unit is pushed onto a
+/// stack of survivors, unless it reacts with the unit on top of the
+/// stack, in which case both are destroyed.
+fn react_fully<R: ReactionRule>(rule: &R, letters: &[R::Unit]) -> Vec<R::Unit> {
+    let mut survivors: Vec<R::Unit> = Vec::with_capacity(letters.len());
+    for &unit in letters {
+        match survivors.last() {
+            Some(&top) if rule.reacts(top, unit) => {
+                survivors.pop();
+            }
+            _ => survivors.push(unit),
+        }
+    }
+    survivors
+}
+
+/// One reaction, as recorded by `react_fully_traced`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Annihilation {
+    /// Positions of the two units in the original polymer.
+    left: usize,
+    right: usize,
+    pair: (char, char),
+    /// Length of the polymer just after this reaction.
+    length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct ReactionTrace {
+    reactions: Vec<Annihilation>,
+    /// Number of reactions which happened after `reactions` was full.
+    omitted: usize,
+}
+
+impl Display for ReactionTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for r in self.reactions.iter() {
+            writeln!(
+                f,
+                "{}{} at positions {} and {} react, leaving {} units",
+                r.pair.0, r.pair.1, r.left, r.right, r.length
+            )?;
+        }
+        if self.omitted > 0 {
+            writeln!(f, "({} further reactions omitted)", self.omitted)?;
+        }
+        Ok(())
+    }
+}
+
+/// Like `react_fully`, but also records the first `limit` reactions.
+fn react_fully_traced<R: ReactionRule>(
+    rule: &R,
+    letters: &[R::Unit],
+    limit: usize,
+) -> (Vec<R::Unit>, ReactionTrace) {
+    let mut trace = ReactionTrace {
+        reactions: Vec::new(),
+        omitted: 0,
+    };
+    // Each survivor is kept with its position in `letters`.
+    let mut survivors: Vec<(usize, R::Unit)> = Vec::with_capacity(letters.len());
+    for (pos, &unit) in letters.iter().enumerate() {
+        match survivors.last() {
+            Some(&(top_pos, top)) if rule.reacts(top, unit) => {
+                survivors.pop();
+                if trace.reactions.len() < limit {
+                    trace.reactions.push(Annihilation {
+                        left: top_pos,
+                        right: pos,
+                        pair: (top.into(), unit.into()),
+                        length: survivors.len() + (letters.len() - pos - 1),
+                    });
+                } else {
+                    trace.omitted += 1;
+                }
+            }
+            _ => survivors.push((pos, unit)),
+        }
+    }
+    (survivors.into_iter().map(|(_, unit)| unit).collect(), trace)
+}
+
+#[test]
+fn test_react_fully_traced() {
+    let (result, trace) = react_fully_traced(&AsciiCasePairs, b"dabAcCaCBAcCcaDA", 10);
+    assert_eq!(result, b"dabCBAcaDA");
+    assert_eq!(trace.omitted, 0);
+    assert_eq!(
+        trace.reactions,
+        vec![
+            Annihilation {
+                left: 4,
+                right: 5,
+                pair: ('c', 'C'),
+                length: 14,
+            },
+            Annihilation {
+                left: 3,
+                right: 6,
+                pair: ('A', 'a'),
+                length: 12,
+            },
+            Annihilation {
+                left: 10,
+                right: 11,
+                pair: ('c', 'C'),
+                length: 10,
+            },
+        ]
+    );
+
+    let (result, trace) = react_fully_traced(&AsciiCasePairs, b"abBA", 1);
+    assert!(result.is_empty());
+    assert_eq!(trace.reactions.len(), 1);
+    assert_eq!(trace.omitted, 1);
+    assert_eq!(
+        trace.to_string(),
+        "bB at positions 1 and 2 react, leaving 2 units\n(1 further reactions omitted)\n"
+    );
+}
+
 #[test]
 fn test_react_fully() {
-    assert_eq!(react_fully("baAB"), "");
-    assert_eq!(&react_fully("a"), "a");
-    assert_eq!(react_fully("aa"), "aa");
-    assert_eq!(react_fully("aA"), "");
-    assert_eq!(react_fully("baA"), "b");
-    assert_eq!(react_fully("aAbB"), "");
+    assert_eq!(react_fully(&AsciiCasePairs, b"baAB"), b"");
+    assert_eq!(react_fully(&AsciiCasePairs, b"a"), b"a");
+    assert_eq!(react_fully(&AsciiCasePairs, b"aa"), b"aa");
+    assert_eq!(react_fully(&AsciiCasePairs, b"aA"), b"");
+    assert_eq!(react_fully(&AsciiCasePairs, b"baA"), b"b");
+    assert_eq!(react_fully(&AsciiCasePairs, b"aAbB"), b"");
+    assert_eq!(react_fully(&AsciiCasePairs, b""), b"");
 }
 
 #[test]
 fn test_provided_example_part1() {
-    assert_eq!(react_fully("dabAcCaCBAcCcaDA"), "dabCBAcaDA");
+    assert_eq!(
+        react_fully(&AsciiCasePairs, b"dabAcCaCBAcCcaDA"),
+        b"dabCBAcaDA"
+    );
 }
 
-fn delete_type(lower: char, upper: char, input: &str) -> String {
+#[test]
+fn test_react_fully_matches_react_fully_by_passes() {
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20181205);
+    // A small alphabet makes reactions (and chains of them) common.
+    const ALPHABET: &[u8] = b"abcABC";
+    for _ in 0..1000 {
+        let len = rng.gen_range(0..200);
+        let polymer: Vec<u8> = (0..len)
+            .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())])
+            .collect();
+        assert_eq!(
+            react_fully(&AsciiCasePairs, &polymer),
+            react_fully_by_passes(&AsciiCasePairs, &polymer),
+            "polymer {}",
+            String::from_utf8_lossy(&polymer)
+        );
+        let (traced, trace) = react_fully_traced(&AsciiCasePairs, &polymer, usize::MAX);
+        assert_eq!(traced, react_fully(&AsciiCasePairs, &polymer));
+        assert_eq!(trace.reactions.len() * 2, polymer.len() - traced.len());
+    }
+}
+
+fn remove_types<R: ReactionRule>(
+    rule: &R,
+    goners: &BTreeSet<R::Unit>,
+    input: &[R::Unit],
+) -> Vec<R::Unit> {
     input
-        .chars()
-        .filter(|&ch| ch != lower && ch != upper)
+        .iter()
+        .copied()
+        .filter(|&unit| !goners.contains(&rule.unit_type(unit)))
         .collect()
 }
 
-fn solve_part2(input: &str) -> Option<(char, usize)> {
-    let all_types: BTreeSet<char> = input.chars().map(|ch| ch.to_ascii_lowercase()).collect();
-    //println!("There are {} types to test deletion", all_types.len());
-    let mut best: Option<(char, usize)> = None;
-    for goner in all_types.into_iter() {
-        //println!("Trying deletion of {goner}");
-        let trial = delete_type(goner, goner.to_ascii_uppercase(), input);
-        let result = react_fully(&trial);
+/// Tries removing every combination of `count` of the unit types
+/// present in `polymer`, and returns the set of types whose removal
+/// leaves the shortest fully-reacted polymer, along with its length.
+fn best_removal<R: ReactionRule>(
+    rule: &R,
+    polymer: &[R::Unit],
+    count: usize,
+) -> Option<(BTreeSet<R::Unit>, usize)> {
+    let all_types: BTreeSet<R::Unit> = polymer.iter().map(|&unit| rule.unit_type(unit)).collect();
+    // Reactions which don't involve the removed types happen
+    // regardless, so each trial can start from the reduced polymer.
+    let reduced = react_fully(rule, polymer);
+    let mut best: Option<(BTreeSet<R::Unit>, usize)> = None;
+    for goners in all_types.into_iter().combinations(count) {
+        let goners: BTreeSet<R::Unit> = goners.into_iter().collect();
+        let result = react_fully(rule, &remove_types(rule, &goners, &reduced));
         match best {
             Some((_, len)) if len < result.len() => (),
-            _ => best = Some((goner, result.len())),
+            _ => best = Some((goners, result.len())),
         }
     }
     best
@@ -119,25 +466,181 @@ fn solve_part2(input: &str) -> Option<(char, usize)> {
 
 #[test]
 fn test_provided_example_part2() {
-    let best = solve_part2("dabAcCaCBAcCcaDA");
+    let best = best_removal(&AsciiCasePairs, b"dabAcCaCBAcCcaDA", 1);
     match best {
         None => {
             panic!("failed to solve part 2 example");
         }
-        Some((ch, len)) => {
-            assert_eq!(ch, 'c');
+        Some((goners, len)) => {
+            assert_eq!(goners, [b'c'].into_iter().collect());
             assert_eq!(len, 4);
         }
     }
 }
 
+#[test]
+fn test_solve_part2_matches_unreduced_trials() {
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20181205);
+    const ALPHABET: &[u8] = b"abcdABCD";
+    for _ in 0..200 {
+        let len = rng.gen_range(1..200);
+        let polymer: Vec<u8> = (0..len)
+            .map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())])
+            .collect();
+        let expected: usize = (b'a'..=b'd')
+            .filter(|goner| {
+                polymer.contains(goner) || polymer.contains(&goner.to_ascii_uppercase())
+            })
+            .map(|goner| {
+                let goners: BTreeSet<u8> = [goner].into_iter().collect();
+                react_fully_by_passes(
+                    &AsciiCasePairs,
+                    &remove_types(&AsciiCasePairs, &goners, &polymer),
+                )
+                .len()
+            })
+            .min()
+            .expect("polymer is not empty");
+        assert_eq!(
+            best_removal(&AsciiCasePairs, &polymer, 1).map(|(_, len)| len),
+            Some(expected),
+            "polymer {}",
+            String::from_utf8_lossy(&polymer)
+        );
+    }
+}
+
+#[test]
+fn test_best_removal() {
+    let polymer = AsciiCasePairs.parse("abxyBA").expect("valid polymer");
+    let (goners, len) = best_removal(&AsciiCasePairs, &polymer, 2).expect("solvable");
+    assert_eq!(goners, [b'x', b'y'].into_iter().collect());
+    assert_eq!(len, 0);
+    let (goners, len) = best_removal(&AsciiCasePairs, &polymer, 4).expect("solvable");
+    assert_eq!(goners.len(), 4);
+    assert_eq!(len, 0);
+    assert!(best_removal(&AsciiCasePairs, &polymer, 5).is_none());
+}
+
+fn parse_pairs(spec: &str) -> Result<PairTable, Fail> {
+    let chars: Vec<char> = spec.chars().collect();
+    if !chars.len().is_multiple_of(2) {
+        return Err(Fail(format!(
+            "reaction pairs '{spec}' should have an even number of characters"
+        )));
+    }
+    let pairs: Vec<(char, char)> = chars.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+    PairTable::new(&pairs)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceFormat {
+    Text,
+    Json,
+}
+
+fn solve<R: ReactionRule>(
+    rule: &R,
+    input: &str,
+    removal_count: usize,
+    trace: Option<(TraceFormat, usize)>,
+) -> Result<(), Fail> {
+    let polymer = rule.parse(input)?;
+    let reacted = match trace {
+        None => react_fully(rule, &polymer),
+        Some((format, limit)) => {
+            // The trace goes to stderr so that the answers on stdout
+            // are unaffected.
+            let (reacted, trace) = react_fully_traced(rule, &polymer, limit);
+            match format {
+                TraceFormat::Text => eprint!("{trace}"),
+                TraceFormat::Json => eprintln!(
+                    "{}",
+                    serde_json::to_string_pretty(&trace).map_err(|e| Fail(e.to_string()))?
+                ),
+            }
+            reacted
+        }
+    };
+    println!("Day 05 part 1: {}", reacted.len());
+    match best_removal(rule, &polymer, removal_count) {
+        Some((_, len)) => {
+            println!("Day 05 part 2: {len}");
+            Ok(())
+        }
+        None => Err(Fail(format!(
+            "the polymer has fewer than {removal_count} unit types"
+        ))),
+    }
+}
+
 fn main() {
+    let m = Command::new("Advent of code 2018 day 5")
+        .about("Solves the day 5 polymer puzzle")
+        .arg(
+            Arg::new("rule")
+                .long("rule")
+                .takes_value(true)
+                .possible_values(["ascii", "unicode"])
+                .default_value("ascii")
+                .help("which units react with each other"),
+        )
+        .arg(
+            Arg::new("pairs")
+                .long("pairs")
+                .takes_value(true)
+                .conflicts_with("rule")
+                .help("react units in pairs given as a string, for example '()[]'"),
+        )
+        .arg(
+            Arg::new("trace")
+                .long("trace")
+                .takes_value(true)
+                .possible_values(["text", "json"])
+                .help("write the reactions of part 1 to standard error"),
+        )
+        .arg(
+            Arg::new("trace-limit")
+                .long("trace-limit")
+                .takes_value(true)
+                .default_value("1000")
+                .help("maximum number of reactions to trace"),
+        )
+        .arg(
+            Arg::new("remove")
+                .long("remove")
+                .takes_value(true)
+                .default_value("1")
+                .help("number of unit types to remove in part 2"),
+        )
+        .get_matches();
+    let removal_count: usize = m
+        .value_of("remove")
+        .expect("has a default")
+        .parse()
+        .expect("--remove should be a number");
+    let trace: Option<(TraceFormat, usize)> = m.value_of("trace").map(|format| {
+        let limit: usize = m
+            .value_of("trace-limit")
+            .expect("has a default")
+            .parse()
+            .expect("--trace-limit should be a number");
+        match format {
+            "json" => (TraceFormat::Json, limit),
+            _ => (TraceFormat::Text, limit),
+        }
+    });
     let input = str::from_utf8(include_bytes!("input.txt"))
         .expect("input file should be correctly encoded")
         .trim();
-    println!("Day 05 part 1: {}", react_fully(input).len());
-    println!(
-        "Day 05 part 2: {}",
-        solve_part2(input).expect("problem should be solvable").1
-    );
+    let result = match (m.value_of("pairs"), m.value_of("rule")) {
+        (Some(spec), _) => {
+            parse_pairs(spec).and_then(|rule| solve(&rule, input, removal_count, trace))
+        }
+        (None, Some("unicode")) => solve(&UnicodeLowercase, input, removal_count, trace),
+        _ => solve(&AsciiCasePairs, input, removal_count, trace),
+    };
+    result.expect("should be able to solve the puzzle");
 }
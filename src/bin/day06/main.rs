@@ -0,0 +1,156 @@
+use std::str;
+
+use clap::{Arg, Command};
+use sscanf::scanf;
+
+use lib::error::Fail;
+use lib::grid::{bounds, Position};
+
+fn parse_coordinates(text: &str) -> Result<Vec<Position>, Fail> {
+    text.split_terminator('\n')
+        .map(|line| line.trim())
+        .enumerate()
+        .map(|(n, line)| match scanf!(line, "{i64}, {i64}") {
+            Ok((x, y)) => Ok(Position { x, y }),
+            Err(e) => Err(Fail(format!(
+                "line number {} '{line}' has wrong format: {e}",
+                n + 1
+            ))),
+        })
+        .collect()
+}
+
+/// Returns the index of the coordinate closest to `p`, or `None` if
+/// two or more coordinates are equally close.
+fn closest(p: &Position, coords: &[Position]) -> Option<usize> {
+    let mut best: Option<(i64, Option<usize>)> = None;
+    for (i, c) in coords.iter().enumerate() {
+        let d = p.manhattan(c);
+        match best {
+            Some((best_d, _)) if best_d < d => (),
+            Some((best_d, _)) if best_d == d => best = Some((d, None)),
+            _ => best = Some((d, Some(i))),
+        }
+    }
+    best.and_then(|(_, i)| i)
+}
+
+/// Returns the area of the region closest to each coordinate, or
+/// `None` for regions which are infinite.  A region is infinite if it
+/// reaches the edge of the bounding box of the coordinates, because
+/// beyond that edge it keeps going forever.
+fn region_areas(coords: &[Position]) -> Vec<Option<usize>> {
+    let mut areas: Vec<Option<usize>> = vec![Some(0); coords.len()];
+    if let Some((min, max)) = bounds(coords) {
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let p = Position { x, y };
+                if let Some(i) = closest(&p, coords) {
+                    if x == min.x || x == max.x || y == min.y || y == max.y {
+                        areas[i] = None;
+                    } else if let Some(area) = areas[i].as_mut() {
+                        *area += 1;
+                    }
+                }
+            }
+        }
+    }
+    areas
+}
+
+fn largest_finite_area(coords: &[Position]) -> Option<usize> {
+    region_areas(coords).into_iter().flatten().max()
+}
+
+/// Counts the locations whose total distance to all the coordinates
+/// is less than `threshold`.
+fn safe_region_size(coords: &[Position], threshold: i64) -> usize {
+    let (min, max) = match bounds(coords) {
+        Some(b) => b,
+        None => {
+            return 0;
+        }
+    };
+    // Each step away from the bounding box adds one to the distance
+    // to every coordinate, so the region can't extend further than
+    // this beyond it.
+    let margin = threshold / (coords.len() as i64) + 1;
+    let mut count = 0;
+    for x in (min.x - margin)..=(max.x + margin) {
+        for y in (min.y - margin)..=(max.y + margin) {
+            let p = Position { x, y };
+            let total: i64 = coords.iter().map(|c| p.manhattan(c)).sum();
+            if total < threshold {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "1, 1\n1, 6\n8, 3\n3, 4\n5, 5\n8, 9\n";
+
+#[test]
+fn test_parse_coordinates() {
+    let coords = parse_coordinates(EXAMPLE).expect("valid example");
+    assert_eq!(coords.len(), 6);
+    assert_eq!(coords[2], Position { x: 8, y: 3 });
+    assert!(parse_coordinates("1, 1\n1 6\n").is_err());
+}
+
+#[test]
+fn test_closest() {
+    let coords = parse_coordinates(EXAMPLE).expect("valid example");
+    assert_eq!(closest(&Position { x: 0, y: 0 }, &coords), Some(0));
+    assert_eq!(closest(&Position { x: 5, y: 2 }, &coords), Some(4));
+    // Equally far from 1,6 and 3,4.
+    assert_eq!(closest(&Position { x: 2, y: 5 }, &coords), None);
+}
+
+#[test]
+fn test_region_areas() {
+    let coords = parse_coordinates(EXAMPLE).expect("valid example");
+    assert_eq!(
+        region_areas(&coords),
+        vec![None, None, None, Some(9), Some(17), None]
+    );
+    assert_eq!(largest_finite_area(&coords), Some(17));
+}
+
+#[test]
+fn test_safe_region_size() {
+    let coords = parse_coordinates(EXAMPLE).expect("valid example");
+    assert_eq!(safe_region_size(&coords, 32), 16);
+    assert_eq!(safe_region_size(&[], 32), 0);
+    // A single coordinate has a diamond-shaped region around it which
+    // reaches well outside the one-point bounding box.
+    assert_eq!(safe_region_size(&[Position { x: 0, y: 0 }], 3), 13);
+}
+
+fn main() {
+    let m = Command::new("Advent of code 2018 day 6")
+        .about("Solves the day 6 puzzle")
+        .arg(
+            Arg::new("threshold")
+                .long("threshold")
+                .takes_value(true)
+                .default_value("10000")
+                .help("total distance below which a location is in the part 2 region"),
+        )
+        .get_matches();
+    let threshold: i64 = m
+        .value_of("threshold")
+        .expect("has a default")
+        .parse()
+        .expect("--threshold should be a number");
+    let coords = parse_coordinates(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!(
+        "Day 06 part 1: {}",
+        largest_finite_area(&coords).expect("there should be a finite region")
+    );
+    println!("Day 06 part 2: {}", safe_region_size(&coords, threshold));
+}
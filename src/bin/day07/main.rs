@@ -0,0 +1,154 @@
+use std::str;
+
+use clap::{Arg, Command};
+use sscanf::scanf;
+
+use lib::error::Fail;
+use lib::graph::Graph;
+
+fn parse_instructions(text: &str) -> Result<Graph<char>, Fail> {
+    let mut graph = Graph::new();
+    for (n, line) in text.split_terminator('\n').enumerate() {
+        match scanf!(
+            line.trim(),
+            "Step {char} must be finished before step {char} can begin."
+        ) {
+            Ok((before, after)) => graph.add_edge(before, after),
+            Err(e) => {
+                return Err(Fail(format!(
+                    "line number {} '{line}' has wrong format: {e}",
+                    n + 1
+                )));
+            }
+        }
+    }
+    Ok(graph)
+}
+
+fn step_duration(base: u64, step: char) -> Result<u64, Fail> {
+    if step.is_ascii_uppercase() {
+        Ok(base + u64::from(step) - u64::from('A') + 1)
+    } else {
+        Err(Fail(format!("step '{step}' is not an upper-case letter")))
+    }
+}
+
+fn solve_part1(graph: &Graph<char>) -> Result<String, Fail> {
+    let order = graph.topological_sort().map_err(|e| Fail(e.to_string()))?;
+    Ok(order.into_iter().collect())
+}
+
+fn solve_part2(graph: &Graph<char>, workers: usize, base: u64) -> Result<u64, Fail> {
+    // Check the step names up front so that the scheduler's duration
+    // function cannot fail.
+    for step in graph.nodes() {
+        step_duration(base, step)?;
+    }
+    let schedule = graph
+        .schedule(workers, |step| {
+            step_duration(base, step).expect("step names were checked")
+        })
+        .map_err(|e| Fail(e.to_string()))?;
+    Ok(schedule.finish_time)
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = concat!(
+    "Step C must be finished before step A can begin.\n",
+    "Step C must be finished before step F can begin.\n",
+    "Step A must be finished before step B can begin.\n",
+    "Step A must be finished before step D can begin.\n",
+    "Step B must be finished before step E can begin.\n",
+    "Step D must be finished before step E can begin.\n",
+    "Step F must be finished before step E can begin.\n",
+);
+
+#[test]
+fn test_parse_instructions() {
+    let graph = parse_instructions(EXAMPLE).expect("valid example");
+    assert_eq!(graph.nodes().collect::<String>(), "ABCDEF");
+    assert_eq!(graph.predecessors('E').collect::<String>(), "BDF");
+    match parse_instructions("Step C must be finished before step A can begin.\nStep C\n") {
+        Err(Fail(msg)) => assert!(msg.starts_with("line number 2 "), "{msg}"),
+        Ok(_) => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn test_step_duration() {
+    assert_eq!(step_duration(60, 'A').expect("valid step"), 61);
+    assert_eq!(step_duration(0, 'Z').expect("valid step"), 26);
+    assert!(step_duration(0, 'a').is_err());
+}
+
+#[test]
+fn test_part1() {
+    let graph = parse_instructions(EXAMPLE).expect("valid example");
+    assert_eq!(solve_part1(&graph).expect("no cycle"), "CABDFE");
+}
+
+#[test]
+fn test_part2() {
+    let graph = parse_instructions(EXAMPLE).expect("valid example");
+    assert_eq!(solve_part2(&graph, 2, 0).expect("no cycle"), 15);
+    match solve_part2(&graph, 0, 0) {
+        Err(Fail(msg)) => assert_eq!(msg, "there are steps to do but no workers"),
+        Ok(t) => panic!("expected an error, got {t}"),
+    }
+}
+
+#[test]
+fn test_cycle() {
+    let graph = parse_instructions(concat!(
+        "Step A must be finished before step B can begin.\n",
+        "Step B must be finished before step A can begin.\n",
+    ))
+    .expect("valid input");
+    match solve_part1(&graph) {
+        Err(Fail(msg)) => assert_eq!(msg, "dependency cycle: A -> B -> A"),
+        Ok(order) => panic!("expected a cycle, got {order}"),
+    }
+    assert!(solve_part2(&graph, 2, 0).is_err());
+}
+
+fn main() {
+    let m = Command::new("Advent of code 2018 day 7")
+        .about("Solves the day 7 puzzle")
+        .arg(
+            Arg::new("workers")
+                .long("workers")
+                .takes_value(true)
+                .default_value("5")
+                .help("number of workers for part 2"),
+        )
+        .arg(
+            Arg::new("base")
+                .long("base")
+                .takes_value(true)
+                .default_value("60")
+                .help("seconds added to the duration of every step in part 2"),
+        )
+        .get_matches();
+    let workers: usize = m
+        .value_of("workers")
+        .expect("has a default")
+        .parse()
+        .expect("--workers should be a number");
+    let base: u64 = m
+        .value_of("base")
+        .expect("has a default")
+        .parse()
+        .expect("--base should be a number");
+    let graph = parse_instructions(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!(
+        "Day 07 part 1: {}",
+        solve_part1(&graph).expect("steps should have an order")
+    );
+    println!(
+        "Day 07 part 2: {}",
+        solve_part2(&graph, workers, base).expect("steps should be schedulable")
+    );
+}
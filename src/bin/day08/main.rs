@@ -0,0 +1,257 @@
+use std::str;
+
+use lib::error::Fail;
+
+/// The licence tree.  Nodes are kept in a flat vector rather than
+/// owning their children directly, so that neither building, walking
+/// nor dropping a very deep tree needs recursion.  Every node is stored
+/// after all of its children.
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<NodeData>,
+}
+
+#[derive(Debug)]
+struct NodeData {
+    children: Vec<usize>,
+    metadata: Vec<u32>,
+}
+
+/// A reference to one node of a `Tree`.
+#[derive(Debug, Clone, Copy)]
+struct Node<'a> {
+    tree: &'a Tree,
+    index: usize,
+}
+
+impl<'a> Node<'a> {
+    fn children(&self) -> impl Iterator<Item = Node<'a>> + 'a {
+        let tree = self.tree;
+        tree.nodes[self.index]
+            .children
+            .iter()
+            .map(move |index| Node {
+                tree,
+                index: *index,
+            })
+    }
+
+    /// Returns the child with the given 1-based number, if there is one.
+    fn child(&self, number: u32) -> Option<Node<'a>> {
+        let index = (number as usize).checked_sub(1)?;
+        self.tree.nodes[self.index]
+            .children
+            .get(index)
+            .map(|index| Node {
+                tree: self.tree,
+                index: *index,
+            })
+    }
+
+    fn metadata(&self) -> &'a [u32] {
+        &self.tree.nodes[self.index].metadata
+    }
+}
+
+impl Tree {
+    fn root(&self) -> Node<'_> {
+        Node {
+            tree: self,
+            index: self.nodes.len() - 1,
+        }
+    }
+
+    fn metadata_sum(&self) -> u64 {
+        let mut total: u64 = 0;
+        let mut pending: Vec<Node> = vec![self.root()];
+        while let Some(node) = pending.pop() {
+            total += node.metadata().iter().map(|m| u64::from(*m)).sum::<u64>();
+            pending.extend(node.children());
+        }
+        total
+    }
+
+    /// Returns the value of the root node.  Since children are stored
+    /// before their parents, we can compute the value of every node in
+    /// a single pass.
+    fn value(&self) -> u64 {
+        let mut values: Vec<u64> = Vec::with_capacity(self.nodes.len());
+        for index in 0..self.nodes.len() {
+            let node = Node { tree: self, index };
+            let value = if node.children().next().is_none() {
+                node.metadata().iter().map(|m| u64::from(*m)).sum()
+            } else {
+                node.metadata()
+                    .iter()
+                    .filter_map(|m| node.child(*m))
+                    .map(|child| values[child.index])
+                    .sum()
+            };
+            values.push(value);
+        }
+        values.last().copied().unwrap_or(0)
+    }
+}
+
+/// A node whose header we have read but which is not yet complete.
+struct Frame {
+    children_left: u32,
+    metadata_count: u32,
+    children: Vec<usize>,
+}
+
+struct Numbers<'a> {
+    tokens: std::iter::Enumerate<str::SplitWhitespace<'a>>,
+    consumed: usize,
+}
+
+impl<'a> Numbers<'a> {
+    fn new(text: &'a str) -> Numbers<'a> {
+        Numbers {
+            tokens: text.split_whitespace().enumerate(),
+            consumed: 0,
+        }
+    }
+
+    /// Reads the next number, which should be `what`.  Numbers are
+    /// counted from zero in error messages.
+    fn next(&mut self, what: &str) -> Result<u32, Fail> {
+        match self.tokens.next() {
+            None => Err(Fail(format!(
+                "input is truncated at number index {}: expected {what}",
+                self.consumed
+            ))),
+            Some((i, token)) => {
+                self.consumed = i + 1;
+                token.parse().map_err(|e| {
+                    Fail(format!(
+                        "number index {i} ('{token}') should be {what} but is not valid: {e}"
+                    ))
+                })
+            }
+        }
+    }
+
+    fn read_header(&mut self) -> Result<Frame, Fail> {
+        let children_left = self.next("the number of child nodes")?;
+        let metadata_count = self.next("the number of metadata entries")?;
+        Ok(Frame {
+            children_left,
+            metadata_count,
+            children: Vec::new(),
+        })
+    }
+}
+
+fn parse_tree(text: &str) -> Result<Tree, Fail> {
+    let mut numbers = Numbers::new(text);
+    let mut nodes: Vec<NodeData> = Vec::new();
+    let mut stack: Vec<Frame> = vec![numbers.read_header()?];
+    while let Some(top) = stack.last_mut() {
+        if top.children_left > 0 {
+            top.children_left -= 1;
+            let child = numbers.read_header()?;
+            stack.push(child);
+            continue;
+        }
+        let frame = stack.pop().expect("stack is not empty");
+        let metadata = (0..frame.metadata_count)
+            .map(|_| numbers.next("a metadata entry"))
+            .collect::<Result<Vec<u32>, Fail>>()?;
+        if let Some(parent) = stack.last_mut() {
+            parent.children.push(nodes.len());
+        }
+        nodes.push(NodeData {
+            children: frame.children,
+            metadata,
+        });
+    }
+    if let Some((i, token)) = numbers.tokens.next() {
+        return Err(Fail(format!(
+            "unexpected '{token}' at number index {i} after the end of the tree"
+        )));
+    }
+    Ok(Tree { nodes })
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2\n";
+
+#[test]
+fn test_parse_tree() {
+    let tree = parse_tree(EXAMPLE).expect("valid example");
+    let root = tree.root();
+    assert_eq!(root.metadata(), &[1, 1, 2]);
+    let children: Vec<Node> = root.children().collect();
+    assert_eq!(children.len(), 2);
+    assert_eq!(children[0].metadata(), &[10, 11, 12]);
+    assert_eq!(children[0].children().count(), 0);
+    let d = root
+        .child(2)
+        .and_then(|c| c.child(1))
+        .expect("C has a child D");
+    assert_eq!(d.metadata(), &[99]);
+    assert!(root.child(0).is_none());
+    assert!(root.child(3).is_none());
+}
+
+#[test]
+fn test_parse_errors() {
+    fn error_of(text: &str) -> String {
+        match parse_tree(text) {
+            Err(Fail(msg)) => msg,
+            Ok(tree) => panic!("expected an error, got {tree:?}"),
+        }
+    }
+    assert_eq!(
+        error_of(""),
+        "input is truncated at number index 0: expected the number of child nodes"
+    );
+    assert_eq!(
+        error_of("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1"),
+        "input is truncated at number index 15: expected a metadata entry"
+    );
+    assert_eq!(
+        error_of("1 1 0"),
+        "input is truncated at number index 3: expected the number of metadata entries"
+    );
+    assert_eq!(
+        error_of("0 1 5 7"),
+        "unexpected '7' at number index 3 after the end of the tree"
+    );
+    assert!(error_of("0 1 x").starts_with("number index 2 ('x') should be a metadata entry"));
+}
+
+#[test]
+fn test_part1() {
+    let tree = parse_tree(EXAMPLE).expect("valid example");
+    assert_eq!(tree.metadata_sum(), 138);
+}
+
+#[test]
+fn test_part2() {
+    let tree = parse_tree(EXAMPLE).expect("valid example");
+    assert_eq!(tree.value(), 66);
+}
+
+#[test]
+fn test_deep_tree() {
+    // A chain of a million nodes, each with one child and one metadata
+    // entry referring to that child.
+    const DEPTH: usize = 1_000_000;
+    let mut text = "1 1 ".repeat(DEPTH);
+    text.push_str("0 1 7");
+    text.push_str(&" 1".repeat(DEPTH));
+    let tree = parse_tree(&text).expect("valid input");
+    assert_eq!(tree.metadata_sum(), 7 + DEPTH as u64);
+    assert_eq!(tree.value(), 7);
+}
+
+fn main() {
+    let tree = parse_tree(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!("Day 08 part 1: {}", tree.metadata_sum());
+    println!("Day 08 part 2: {}", tree.value());
+}
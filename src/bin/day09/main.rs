@@ -0,0 +1,114 @@
+use std::str;
+
+use sscanf::scanf;
+
+use lib::error::Fail;
+use lib::ring::Ring;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Game {
+    players: usize,
+    last_marble: u32,
+}
+
+fn parse_game(text: &str) -> Result<Game, Fail> {
+    let line = text.trim();
+    match scanf!(line, "{usize} players; last marble is worth {u32} points") {
+        Ok((players, last_marble)) if players > 0 => Ok(Game {
+            players,
+            last_marble,
+        }),
+        Ok(_) => Err(Fail("there must be at least one player".to_string())),
+        Err(e) => Err(Fail(format!("input '{line}' has wrong format: {e}"))),
+    }
+}
+
+/// Returns the same game with the last marble worth `factor` times
+/// as much.
+fn scaled(game: &Game, factor: u32) -> Result<Game, Fail> {
+    match game.last_marble.checked_mul(factor) {
+        Some(last_marble) => Ok(Game {
+            last_marble,
+            ..*game
+        }),
+        None => Err(Fail(format!(
+            "a last marble worth {} times {factor} points is too large",
+            game.last_marble
+        ))),
+    }
+}
+
+#[test]
+fn test_scaled() {
+    let game = Game {
+        players: 9,
+        last_marble: 25,
+    };
+    assert_eq!(scaled(&game, 100).expect("small enough").last_marble, 2500);
+    let huge = Game {
+        players: 9,
+        last_marble: u32::MAX / 10,
+    };
+    assert!(scaled(&huge, 100).is_err());
+}
+
+fn high_score(game: &Game) -> u64 {
+    let mut scores: Vec<u64> = vec![0; game.players];
+    let mut circle: Ring<u32> = Ring::with_capacity(game.last_marble as usize + 1);
+    circle.insert(0);
+    for marble in 1..=game.last_marble {
+        if marble.is_multiple_of(23) {
+            circle.rotate(-7);
+            let removed = circle.remove().expect("the circle is never empty here");
+            scores[marble as usize % game.players] += u64::from(marble) + u64::from(removed);
+        } else {
+            circle.rotate(1);
+            circle.insert(marble);
+        }
+    }
+    scores.into_iter().max().unwrap_or(0)
+}
+
+#[test]
+fn test_parse_game() {
+    assert_eq!(
+        parse_game("10 players; last marble is worth 1618 points\n").expect("valid input"),
+        Game {
+            players: 10,
+            last_marble: 1618
+        }
+    );
+    assert!(parse_game("0 players; last marble is worth 1618 points").is_err());
+    assert!(parse_game("10 players").is_err());
+}
+
+#[test]
+fn test_high_score() {
+    for (players, last_marble, expected) in [
+        (9, 25, 32),
+        (10, 1618, 8317),
+        (13, 7999, 146373),
+        (17, 1104, 2764),
+        (21, 6111, 54718),
+        (30, 5807, 37305),
+    ] {
+        assert_eq!(
+            high_score(&Game {
+                players,
+                last_marble
+            }),
+            expected,
+            "{players} players, last marble {last_marble}"
+        );
+    }
+}
+
+fn main() {
+    let game = parse_game(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!("Day 09 part 1: {}", high_score(&game));
+    let bigger = scaled(&game, 100).expect("part 2 game should not be too large");
+    println!("Day 09 part 2: {}", high_score(&bigger));
+}
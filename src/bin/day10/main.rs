@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::str;
+
+use regex::Regex;
+
+use lib::error::Fail;
+use lib::grid::{bounds, Position};
+use lib::ocr::recognise;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Point {
+    position: Position,
+    dx: i64,
+    dy: i64,
+}
+
+impl Point {
+    fn at(&self, t: i64) -> Position {
+        Position {
+            x: self.position.x + self.dx * t,
+            y: self.position.y + self.dy * t,
+        }
+    }
+}
+
+const POINT_REGEX: &str = r"^position=<\s*(-?\d+),\s*(-?\d+)> velocity=<\s*(-?\d+),\s*(-?\d+)>$";
+
+fn parse_points(text: &str) -> Result<Vec<Point>, Fail> {
+    let rx = Regex::new(POINT_REGEX).map_err(|e| Fail(e.to_string()))?;
+    text.split_terminator('\n')
+        .map(|line| line.trim())
+        .enumerate()
+        .map(|(n, line)| {
+            let invalid = || Fail(format!("line number {} '{line}' has wrong format", n + 1));
+            let captures = rx.captures(line).ok_or_else(invalid)?;
+            let field = |i: usize| -> Result<i64, Fail> {
+                captures
+                    .get(i)
+                    .and_then(|m| m.as_str().parse().ok())
+                    .ok_or_else(invalid)
+            };
+            Ok(Point {
+                position: Position {
+                    x: field(1)?,
+                    y: field(2)?,
+                },
+                dx: field(3)?,
+                dy: field(4)?,
+            })
+        })
+        .collect()
+}
+
+fn positions_at(points: &[Point], t: i64) -> Vec<Position> {
+    points.iter().map(|p| p.at(t)).collect()
+}
+
+fn area_at(points: &[Point], t: i64) -> i128 {
+    match bounds(&positions_at(points, t)) {
+        Some((min, max)) => i128::from(max.x - min.x + 1) * i128::from(max.y - min.y + 1),
+        None => 0,
+    }
+}
+
+/// Returns the earliest time (not before 0) at which the bounding box
+/// of the points is smallest.
+///
+/// The width and height of the bounding box each shrink until the
+/// points pass their closest approach and grow afterwards, so the area
+/// is smallest at the first time `t` at which `area(t + 1)` is no
+/// smaller than `area(t)`.  We find that by binary search on the sign
+/// of the change in area, rather than stepping one second at a time.
+fn convergence_time(points: &[Point]) -> i64 {
+    let shrinking = |t: i64| area_at(points, t + 1) < area_at(points, t);
+    // First find a time by which the area has stopped shrinking.
+    let mut high: i64 = 1;
+    while shrinking(high) {
+        high *= 2;
+    }
+    // Invariant: the area is no longer shrinking at `high`, and is
+    // still shrinking at every time before `low`.
+    let mut low: i64 = 0;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if shrinking(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Draws the points with '#' for each lit pixel and '.' elsewhere.
+fn render(positions: &[Position]) -> String {
+    let (min, max) = match bounds(positions) {
+        Some(b) => b,
+        None => {
+            return String::new();
+        }
+    };
+    let lit: HashSet<&Position> = positions.iter().collect();
+    (min.y..=max.y)
+        .map(|y| {
+            (min.x..=max.x)
+                .map(|x| {
+                    if lit.contains(&Position { x, y }) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = concat!(
+    "position=< 9,  1> velocity=< 0,  2>\n",
+    "position=< 7,  0> velocity=<-1,  0>\n",
+    "position=< 3, -2> velocity=<-1,  1>\n",
+    "position=< 6, 10> velocity=<-2, -1>\n",
+    "position=< 2, -4> velocity=< 2,  2>\n",
+    "position=<-6, 10> velocity=< 2, -2>\n",
+    "position=< 1,  8> velocity=< 1, -1>\n",
+    "position=< 1,  7> velocity=< 1,  0>\n",
+    "position=<-3, 11> velocity=< 1, -2>\n",
+    "position=< 7,  6> velocity=<-1, -1>\n",
+    "position=<-2,  3> velocity=< 1,  0>\n",
+    "position=<-4,  3> velocity=< 2,  0>\n",
+    "position=<10, -3> velocity=<-1,  1>\n",
+    "position=< 5, 11> velocity=< 1, -2>\n",
+    "position=< 4,  7> velocity=< 0, -1>\n",
+    "position=< 8, -2> velocity=< 0,  1>\n",
+    "position=<15,  0> velocity=<-2,  0>\n",
+    "position=< 1,  6> velocity=< 1,  0>\n",
+    "position=< 8,  9> velocity=< 0, -1>\n",
+    "position=< 3,  3> velocity=<-1,  1>\n",
+    "position=< 0,  5> velocity=< 0, -1>\n",
+    "position=<-2,  2> velocity=< 2,  0>\n",
+    "position=< 5, -2> velocity=< 1,  2>\n",
+    "position=< 1,  4> velocity=< 2,  1>\n",
+    "position=<-2,  7> velocity=< 2, -2>\n",
+    "position=< 3,  6> velocity=<-1, -1>\n",
+    "position=< 5,  0> velocity=< 1,  0>\n",
+    "position=<-6,  0> velocity=< 2,  0>\n",
+    "position=< 5,  9> velocity=< 1, -2>\n",
+    "position=<14,  7> velocity=<-2,  0>\n",
+    "position=<-3,  6> velocity=< 2, -1>\n",
+);
+
+#[test]
+fn test_parse_points() {
+    let points = parse_points(EXAMPLE).expect("valid example");
+    assert_eq!(points.len(), 31);
+    assert_eq!(
+        points[2],
+        Point {
+            position: Position { x: 3, y: -2 },
+            dx: -1,
+            dy: 1
+        }
+    );
+    assert!(parse_points("position=< 9,  1> velocity=< 0>\n").is_err());
+}
+
+#[test]
+fn test_example() {
+    let points = parse_points(EXAMPLE).expect("valid example");
+    let t = convergence_time(&points);
+    assert_eq!(t, 3);
+    assert_eq!(
+        render(&positions_at(&points, t)),
+        concat!(
+            "#...#..###\n",
+            "#...#...#.\n",
+            "#...#...#.\n",
+            "#####...#.\n",
+            "#...#...#.\n",
+            "#...#...#.\n",
+            "#...#...#.\n",
+            "#...#..###",
+        )
+    );
+}
+
+#[test]
+fn test_convergence_and_ocr() {
+    use rand::{Rng, SeedableRng};
+    // Scatter the pixels of a message backwards in time, and check
+    // that we can find it again.
+    const WHEN: i64 = 10_000;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20181210);
+    let message = concat!(
+        "#....#..######..#.......#####.\n",
+        "#....#..#.......#.......#....#\n",
+        "#....#..#.......#.......#....#\n",
+        "#....#..#.......#.......#....#\n",
+        "######..#####...#.......#####.\n",
+        "#....#..#.......#.......#.....\n",
+        "#....#..#.......#.......#.....\n",
+        "#....#..#.......#.......#.....\n",
+        "#....#..#.......#.......#.....\n",
+        "#....#..######..######..#.....\n",
+    );
+    let mut points: Vec<Point> = Vec::new();
+    for (y, row) in message.lines().enumerate() {
+        for (x, ch) in row.chars().enumerate() {
+            if ch == '#' {
+                let (dx, dy) = (rng.gen_range(-5..=5), rng.gen_range(-5..=5));
+                let target = Position {
+                    x: x as i64,
+                    y: y as i64,
+                };
+                points.push(Point {
+                    position: Position {
+                        x: target.x - dx * WHEN,
+                        y: target.y - dy * WHEN,
+                    },
+                    dx,
+                    dy,
+                });
+            }
+        }
+    }
+    let t = convergence_time(&points);
+    assert_eq!(t, WHEN);
+    let picture = render(&positions_at(&points, t));
+    assert_eq!(recognise(&picture), Ok("HELP".to_string()));
+}
+
+#[test]
+fn test_convergence_time_degenerate() {
+    assert_eq!(convergence_time(&[]), 0);
+    let still = Point {
+        position: Position { x: 1, y: 1 },
+        dx: 0,
+        dy: 0,
+    };
+    assert_eq!(convergence_time(&[still]), 0);
+    let diverging = Point {
+        position: Position { x: 2, y: 2 },
+        dx: 1,
+        dy: 1,
+    };
+    assert_eq!(convergence_time(&[still, diverging]), 0);
+}
+
+fn main() {
+    let points = parse_points(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    let t = convergence_time(&points);
+    let picture = render(&positions_at(&points, t));
+    match recognise(&picture) {
+        Ok(message) => println!("Day 10 part 1: {message}"),
+        Err(e) => println!("Day 10 part 1: could not read the message ({e}):\n{picture}"),
+    }
+    println!("Day 10 part 2: {t}");
+}
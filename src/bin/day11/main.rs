@@ -0,0 +1,129 @@
+use clap::{Arg, Command};
+
+use lib::grid::SummedAreaTable;
+
+const GRID_SIZE: usize = 300;
+
+/// Returns the power level of the fuel cell at (`x`, `y`), using the
+/// puzzle's 1-based coordinates.
+fn power_level(x: i64, y: i64, serial: i64) -> i64 {
+    let rack_id = x + 10;
+    let power = (rack_id * y + serial) * rack_id;
+    (power / 100) % 10 - 5
+}
+
+#[test]
+fn test_power_level() {
+    assert_eq!(power_level(3, 5, 8), 4);
+    assert_eq!(power_level(122, 79, 57), -5);
+    assert_eq!(power_level(217, 196, 39), 0);
+    assert_eq!(power_level(101, 153, 71), 4);
+}
+
+fn power_table(serial: i64) -> SummedAreaTable<i64> {
+    SummedAreaTable::new(GRID_SIZE, GRID_SIZE, |x, y| {
+        power_level(x as i64 + 1, y as i64 + 1, serial)
+    })
+}
+
+/// A square of fuel cells; `x` and `y` are the 1-based coordinates of
+/// its top left cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Square {
+    x: usize,
+    y: usize,
+    size: usize,
+    power: i64,
+}
+
+/// Returns the square with the largest total power among those of
+/// the given sizes.  Ties go to the first square found, scanning sizes
+/// in order, then columns, then rows.
+fn best_square<I>(table: &SummedAreaTable<i64>, sizes: I) -> Option<Square>
+where
+    I: IntoIterator<Item = usize>,
+{
+    let mut best: Option<Square> = None;
+    for size in sizes {
+        for left in 0..=table.width().saturating_sub(size) {
+            for top in 0..=table.height().saturating_sub(size) {
+                if let Some(power) = table.rectangle_sum(left, top, size, size) {
+                    if best.is_none_or(|b| power > b.power) {
+                        best = Some(Square {
+                            x: left + 1,
+                            y: top + 1,
+                            size,
+                            power,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+#[test]
+fn test_part1() {
+    assert_eq!(
+        best_square(&power_table(18), [3]),
+        Some(Square {
+            x: 33,
+            y: 45,
+            size: 3,
+            power: 29
+        })
+    );
+    assert_eq!(
+        best_square(&power_table(42), [3]),
+        Some(Square {
+            x: 21,
+            y: 61,
+            size: 3,
+            power: 30
+        })
+    );
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(
+        best_square(&power_table(18), 1..=GRID_SIZE),
+        Some(Square {
+            x: 90,
+            y: 269,
+            size: 16,
+            power: 113
+        })
+    );
+    assert_eq!(
+        best_square(&power_table(42), 1..=GRID_SIZE),
+        Some(Square {
+            x: 232,
+            y: 251,
+            size: 12,
+            power: 119
+        })
+    );
+}
+
+fn main() {
+    let m = Command::new("Advent of code 2018 day 11")
+        .about("Solves the day 11 puzzle")
+        .arg(
+            Arg::new("serial")
+                .required(true)
+                .help("grid serial number (the puzzle input)"),
+        )
+        .get_matches();
+    let serial: i64 = m
+        .value_of("serial")
+        .expect("is required")
+        .parse()
+        .expect("the serial number should be a number");
+    let table = power_table(serial);
+    let part1 = best_square(&table, [3]).expect("the grid is bigger than 3x3");
+    println!("Day 11 part 1: {},{}", part1.x, part1.y);
+    let part2 = best_square(&table, 1..=GRID_SIZE).expect("the grid is not empty");
+    println!("Day 11 part 2: {},{},{}", part2.x, part2.y, part2.size);
+}
@@ -0,0 +1,84 @@
+use std::str;
+
+use lib::automaton::{Automaton, Rules};
+use lib::error::Fail;
+
+fn parse_input(text: &str) -> Result<Automaton, Fail> {
+    let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let initial = first
+        .trim()
+        .strip_prefix("initial state: ")
+        .ok_or_else(|| Fail(format!("expected an initial state, got '{first}'")))?;
+    // The rules start on the line after the initial state.
+    let rules = Rules::parse(rest, 2).map_err(|Fail(msg)| Fail(format!("in rules: {msg}")))?;
+    Automaton::new(rules, initial)
+}
+
+fn pot_sum_after(automaton: &Automaton, generations: u64) -> i64 {
+    let mut automaton = automaton.clone();
+    automaton.advance_to(generations);
+    automaton.live_cells().sum()
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = concat!(
+    "initial state: #..#.#..##......###...###\n",
+    "\n",
+    "...## => #\n",
+    "..#.. => #\n",
+    ".#... => #\n",
+    ".#.#. => #\n",
+    ".#.## => #\n",
+    ".##.. => #\n",
+    ".#### => #\n",
+    "#.#.# => #\n",
+    "#.### => #\n",
+    "##.#. => #\n",
+    "##.## => #\n",
+    "###.. => #\n",
+    "###.# => #\n",
+    "####. => #\n",
+);
+
+#[test]
+fn test_parse_input() {
+    let automaton = parse_input(EXAMPLE).expect("valid example");
+    assert_eq!(automaton.to_string(), "#..#.#..##......###...###");
+    assert!(parse_input("#..#\n\n...## => #\n").is_err());
+    match parse_input("initial state: #..#\n\n...## =>#\n") {
+        Err(Fail(msg)) => assert!(msg.starts_with("in rules: line 3 "), "{msg}"),
+        Ok(a) => panic!("expected an error, got {a:?}"),
+    }
+}
+
+#[test]
+fn test_part1() {
+    let automaton = parse_input(EXAMPLE).expect("valid example");
+    assert_eq!(pot_sum_after(&automaton, 20), 325);
+}
+
+#[test]
+fn test_part2() {
+    // The example settles into a pattern which moves one pot to the
+    // right each generation.
+    let automaton = parse_input(EXAMPLE).expect("valid example");
+    let sum_at = |g: u64| pot_sum_after(&automaton, g);
+    let pots = sum_at(1001) - sum_at(1000);
+    assert_eq!(sum_at(1002) - sum_at(1001), pots);
+    assert_eq!(
+        sum_at(50_000_000_000),
+        sum_at(1000) + pots * (50_000_000_000 - 1000)
+    );
+}
+
+fn main() {
+    let automaton = parse_input(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!("Day 12 part 1: {}", pot_sum_after(&automaton, 20));
+    println!(
+        "Day 12 part 2: {}",
+        pot_sum_after(&automaton, 50_000_000_000)
+    );
+}
@@ -0,0 +1,380 @@
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+use std::str;
+
+use clap::{Arg, Command};
+
+use lib::error::Fail;
+use lib::grid::{CompassDirection, Grid, Position};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Track {
+    Empty,
+    Vertical,
+    Horizontal,
+    /// A '/' curve.
+    Slash,
+    /// A '\' curve.
+    Backslash,
+    Intersection,
+}
+
+impl From<Track> for char {
+    fn from(t: Track) -> char {
+        match t {
+            Track::Empty => ' ',
+            Track::Vertical => '|',
+            Track::Horizontal => '-',
+            Track::Slash => '/',
+            Track::Backslash => '\\',
+            Track::Intersection => '+',
+        }
+    }
+}
+
+/// What a cart does at the next intersection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Turn {
+    Left,
+    Straight,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Cart {
+    position: Position,
+    direction: CompassDirection,
+    next_turn: Turn,
+}
+
+fn cart_symbol(d: CompassDirection) -> char {
+    match d {
+        CompassDirection::North => '^',
+        CompassDirection::South => 'v',
+        CompassDirection::West => '<',
+        CompassDirection::East => '>',
+    }
+}
+
+impl Cart {
+    /// Moves the cart one step along `tracks`.
+    fn advance(&mut self, tracks: &Grid<Track>) -> Result<(), Fail> {
+        use CompassDirection::*;
+        self.position = self.position.move_direction(&self.direction);
+        self.direction = match (tracks.get(&self.position), self.direction) {
+            (Some(Track::Vertical), North | South) | (Some(Track::Horizontal), East | West) => {
+                self.direction
+            }
+            (Some(Track::Slash), North | South) | (Some(Track::Backslash), East | West) => {
+                self.direction.turn_right()
+            }
+            (Some(Track::Slash), East | West) | (Some(Track::Backslash), North | South) => {
+                self.direction.turn_left()
+            }
+            (Some(Track::Intersection), d) => {
+                let (d, next_turn) = match self.next_turn {
+                    Turn::Left => (d.turn_left(), Turn::Straight),
+                    Turn::Straight => (d, Turn::Right),
+                    Turn::Right => (d.turn_right(), Turn::Left),
+                };
+                self.next_turn = next_turn;
+                d
+            }
+            _ => {
+                return Err(Fail(format!(
+                    "cart moving {} left the track at {}",
+                    char::from(self.direction),
+                    self.position
+                )));
+            }
+        };
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Mine {
+    tracks: Grid<Track>,
+    carts: Vec<Cart>,
+    /// Where carts collided during the most recent tick.
+    crashes: Vec<Position>,
+}
+
+impl Mine {
+    fn parse(text: &str) -> Result<Mine, Fail> {
+        let mut carts: Vec<Cart> = Vec::new();
+        let tracks = Grid::from_text(text, Track::Empty, |position, ch| {
+            let mut cart = |direction: CompassDirection| {
+                carts.push(Cart {
+                    position,
+                    direction,
+                    next_turn: Turn::Left,
+                })
+            };
+            match ch {
+                ' ' => Ok(Track::Empty),
+                '|' => Ok(Track::Vertical),
+                '-' => Ok(Track::Horizontal),
+                '/' => Ok(Track::Slash),
+                '\\' => Ok(Track::Backslash),
+                '+' => Ok(Track::Intersection),
+                '^' => {
+                    cart(CompassDirection::North);
+                    Ok(Track::Vertical)
+                }
+                'v' => {
+                    cart(CompassDirection::South);
+                    Ok(Track::Vertical)
+                }
+                '<' => {
+                    cart(CompassDirection::West);
+                    Ok(Track::Horizontal)
+                }
+                '>' => {
+                    cart(CompassDirection::East);
+                    Ok(Track::Horizontal)
+                }
+                _ => Err(Fail(format!("unexpected character '{ch}' at {position}"))),
+            }
+        })?;
+        Ok(Mine {
+            tracks,
+            carts,
+            crashes: Vec::new(),
+        })
+    }
+
+    /// Moves every cart one step, in reading order.  Carts which
+    /// collide are removed at once, so later carts can pass through
+    /// the wreckage.  Returns the positions of any collisions.
+    fn tick(&mut self) -> Result<&[Position], Fail> {
+        self.carts.sort_by_key(|cart| cart.position.reading_order());
+        self.crashes.clear();
+        let mut crashed: Vec<bool> = vec![false; self.carts.len()];
+        for i in 0..self.carts.len() {
+            if crashed[i] {
+                continue;
+            }
+            self.carts[i].advance(&self.tracks)?;
+            let here = self.carts[i].position;
+            if let Some(j) = (0..self.carts.len())
+                .find(|j| *j != i && !crashed[*j] && self.carts[*j].position == here)
+            {
+                crashed[i] = true;
+                crashed[j] = true;
+                self.crashes.push(here);
+            }
+        }
+        let mut crashed = crashed.into_iter();
+        self.carts
+            .retain(|_| !crashed.next().expect("one flag per cart"));
+        Ok(&self.crashes)
+    }
+}
+
+impl Display for Mine {
+    /// Draws the map with the carts on it, marking this tick's
+    /// collisions with 'X'.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for y in 0..self.tracks.height() as i64 {
+            let row: String = (0..self.tracks.width() as i64)
+                .map(|x| {
+                    let p = Position { x, y };
+                    if self.crashes.contains(&p) {
+                        'X'
+                    } else if let Some(cart) = self.carts.iter().find(|c| c.position == p) {
+                        cart_symbol(cart.direction)
+                    } else {
+                        self.tracks.get(&p).map(|t| char::from(*t)).unwrap_or(' ')
+                    }
+                })
+                .collect();
+            writeln!(f, "{}", row.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the simulation until `done` says to stop, returning the
+/// result it gives.  If `show` is set, the map is printed on the
+/// standard error before the first tick and after each one.
+fn run_until<F, T>(mine: &mut Mine, show: bool, mut done: F) -> Result<T, Fail>
+where
+    F: FnMut(&Mine) -> Option<Result<T, Fail>>,
+{
+    // Carts which get back to a configuration they have been in before
+    // will loop for ever.
+    let mut seen: HashSet<Vec<Cart>> = HashSet::new();
+    if show {
+        eprintln!("{mine}");
+    }
+    loop {
+        mine.tick()?;
+        if show {
+            eprintln!("{mine}");
+        }
+        if let Some(result) = done(mine) {
+            return result;
+        }
+        if !seen.insert(mine.carts.clone()) {
+            return Err(Fail(
+                "the carts are going round in circles without colliding".to_string(),
+            ));
+        }
+    }
+}
+
+fn first_collision(mut mine: Mine, show: bool) -> Result<Position, Fail> {
+    run_until(&mut mine, show, |mine| match mine.crashes.first() {
+        Some(p) => Some(Ok(*p)),
+        None if mine.carts.len() < 2 => Some(Err(Fail(
+            "there are too few carts for a collision".to_string(),
+        ))),
+        None => None,
+    })
+}
+
+fn last_cart(mut mine: Mine, show: bool) -> Result<Position, Fail> {
+    run_until(&mut mine, show, |mine| match mine.carts.as_slice() {
+        [] => Some(Err(Fail("all the carts crashed".to_string()))),
+        [cart] => Some(Ok(cart.position)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+const EXAMPLE1: &str = concat!(
+    r"/->-\        ",
+    "\n",
+    r"|   |  /----\",
+    "\n",
+    r"| /-+--+-\  |",
+    "\n",
+    r"| | |  | v  |",
+    "\n",
+    r"\-+-/  \-+--/",
+    "\n",
+    r"  \------/   ",
+    "\n",
+);
+
+#[cfg(test)]
+const EXAMPLE2: &str = concat!(
+    r"/>-<\  ", "\n", r"|   |  ", "\n", r"| /<+-\", "\n", r"| | | v", "\n", r"\>+</ |", "\n",
+    r"  |   ^", "\n", r"  \<->/", "\n",
+);
+
+#[test]
+fn test_parse() {
+    let mine = Mine::parse(EXAMPLE1).expect("valid example");
+    assert_eq!(mine.tracks.width(), 13);
+    assert_eq!(mine.tracks.height(), 6);
+    assert_eq!(mine.carts.len(), 2);
+    assert_eq!(mine.carts[1].position, Position { x: 9, y: 3 });
+    assert_eq!(mine.carts[1].direction, CompassDirection::South);
+    assert_eq!(
+        mine.tracks.get(&Position { x: 9, y: 3 }),
+        Some(&Track::Vertical)
+    );
+    // Drawing the map gives back the input, without trailing spaces.
+    let trimmed: String = EXAMPLE1
+        .lines()
+        .map(|line| format!("{}\n", line.trim_end()))
+        .collect();
+    assert_eq!(mine.to_string(), trimmed);
+    assert!(Mine::parse("-->-x\n").is_err());
+}
+
+#[test]
+fn test_tick() {
+    let mut mine = Mine::parse(EXAMPLE1).expect("valid example");
+    assert!(mine.tick().expect("carts stay on the track").is_empty());
+    assert_eq!(
+        mine.to_string(),
+        concat!(
+            r"/-->\",
+            "\n",
+            r"|   |  /----\",
+            "\n",
+            r"| /-+--+-\  |",
+            "\n",
+            r"| | |  | |  |",
+            "\n",
+            r"\-+-/  \->--/",
+            "\n",
+            r"  \------/",
+            "\n",
+        )
+    );
+    let mut derailed = Mine::parse("->\n").expect("valid map");
+    match derailed.tick() {
+        Err(Fail(msg)) => assert_eq!(msg, "cart moving E left the track at 2,0"),
+        Ok(crashes) => panic!("expected a derailment, got {crashes:?}"),
+    }
+}
+
+#[test]
+fn test_part1() {
+    let mine = Mine::parse(EXAMPLE1).expect("valid example");
+    assert_eq!(
+        first_collision(mine, false).expect("carts collide"),
+        Position { x: 7, y: 3 }
+    );
+    // Head-on, and from behind into a cart which has not moved yet.
+    let mine = Mine::parse("->-<-\n").expect("valid map");
+    assert_eq!(
+        first_collision(mine, false).expect("carts collide"),
+        Position { x: 2, y: 0 }
+    );
+    let mine = Mine::parse("->>--\n").expect("valid map");
+    assert_eq!(
+        first_collision(mine, false).expect("carts collide"),
+        Position { x: 2, y: 0 }
+    );
+}
+
+#[test]
+fn test_no_collision() {
+    let lonely = Mine::parse("/>\\\n\\-/\n").expect("valid map");
+    assert!(first_collision(lonely, false).is_err());
+    let separate = Mine::parse("/>\\ /<\\\n\\-/ \\-/\n").expect("valid map");
+    match first_collision(separate, false) {
+        Err(Fail(msg)) => assert!(msg.contains("round in circles"), "{msg}"),
+        Ok(p) => panic!("expected no collision, got {p}"),
+    }
+}
+
+#[test]
+fn test_part2() {
+    let mine = Mine::parse(EXAMPLE2).expect("valid example");
+    assert_eq!(
+        last_cart(mine, false).expect("one cart survives"),
+        Position { x: 6, y: 4 }
+    );
+    let mine = Mine::parse("->-<-\n").expect("valid map");
+    assert!(last_cart(mine, false).is_err());
+}
+
+fn main() {
+    let m = Command::new("Advent of code 2018 day 13")
+        .about("Solves the day 13 puzzle")
+        .arg(
+            Arg::new("show-ticks")
+                .long("show-ticks")
+                .help("print the map after each tick on the standard error"),
+        )
+        .get_matches();
+    let show = m.is_present("show-ticks");
+    let mine = Mine::parse(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!(
+        "Day 13 part 1: {}",
+        first_collision(mine.clone(), show).expect("carts should collide")
+    );
+    println!(
+        "Day 13 part 2: {}",
+        last_cart(mine, show).expect("one cart should survive")
+    );
+}
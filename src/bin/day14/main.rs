@@ -0,0 +1,226 @@
+use std::str;
+
+use clap::{Arg, Command};
+
+use lib::error::Fail;
+
+/// The recipe scores, one digit per byte.
+struct Scoreboard {
+    scores: Vec<u8>,
+    elves: [usize; 2],
+}
+
+impl Scoreboard {
+    fn with_capacity(capacity: usize) -> Scoreboard {
+        let mut scores = Vec::with_capacity(capacity.max(2));
+        scores.extend_from_slice(&[3, 7]);
+        Scoreboard {
+            scores,
+            elves: [0, 1],
+        }
+    }
+
+    /// Makes new recipes, returning the one or two digits which were
+    /// appended to the scoreboard.
+    fn step(&mut self) -> &[u8] {
+        let before = self.scores.len();
+        let [a, b] = self.elves.map(|elf| self.scores[elf]);
+        let total = a + b;
+        if total >= 10 {
+            self.scores.push(total / 10);
+        }
+        self.scores.push(total % 10);
+        let len = self.scores.len();
+        for elf in self.elves.iter_mut() {
+            *elf = (*elf + 1 + usize::from(self.scores[*elf])) % len;
+        }
+        &self.scores[before..]
+    }
+
+    fn len(&self) -> usize {
+        self.scores.len()
+    }
+}
+
+fn parse_digits(text: &str) -> Result<Vec<u8>, Fail> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(Fail("input is empty".to_string()));
+    }
+    text.chars()
+        .map(|ch| {
+            ch.to_digit(10)
+                .map(|d| d as u8)
+                .ok_or_else(|| Fail(format!("'{ch}' is not a digit")))
+        })
+        .collect()
+}
+
+/// Returns the ten scores after the first `count` recipes.
+fn scores_after(count: usize) -> String {
+    let mut board = Scoreboard::with_capacity(count + 11);
+    while board.len() < count + 10 {
+        board.step();
+    }
+    board.scores[count..count + 10]
+        .iter()
+        .map(|d| char::from(b'0' + d))
+        .collect()
+}
+
+/// Finds a digit sequence in a stream of digits, one digit at a time,
+/// using the Knuth-Morris-Pratt failure function so that no digit is
+/// looked at more than a fixed number of times.
+struct SequenceMatcher {
+    target: Vec<u8>,
+    // fallback[i] is the length of the longest proper prefix of
+    // target[..=i] which is also a suffix of it.
+    fallback: Vec<usize>,
+    matched: usize,
+}
+
+impl SequenceMatcher {
+    fn new(target: &[u8]) -> Result<SequenceMatcher, Fail> {
+        if target.is_empty() {
+            return Err(Fail("the sequence to find is empty".to_string()));
+        }
+        let mut fallback = vec![0; target.len()];
+        let mut k = 0;
+        for i in 1..target.len() {
+            while k > 0 && target[i] != target[k] {
+                k = fallback[k - 1];
+            }
+            if target[i] == target[k] {
+                k += 1;
+            }
+            fallback[i] = k;
+        }
+        Ok(SequenceMatcher {
+            target: target.to_vec(),
+            fallback,
+            matched: 0,
+        })
+    }
+
+    /// Feeds the next digit of the stream, returning true if the
+    /// stream now ends with the target sequence.
+    fn feed(&mut self, digit: u8) -> bool {
+        if self.matched == self.target.len() {
+            self.matched = self.fallback[self.matched - 1];
+        }
+        while self.matched > 0 && self.target[self.matched] != digit {
+            self.matched = self.fallback[self.matched - 1];
+        }
+        if self.target[self.matched] == digit {
+            self.matched += 1;
+        }
+        self.matched == self.target.len()
+    }
+}
+
+#[test]
+fn test_sequence_matcher() {
+    let stream = [1, 2, 1, 2, 1, 3, 1, 2, 1, 2, 1, 2, 1, 3];
+    let mut matcher = SequenceMatcher::new(&[1, 2, 1, 3]).expect("non-empty target");
+    let ends: Vec<usize> = stream
+        .iter()
+        .enumerate()
+        .filter(|(_, d)| matcher.feed(**d))
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(ends, vec![5, 13]);
+    let mut overlapping = SequenceMatcher::new(&[7, 7]).expect("non-empty target");
+    let hits: Vec<bool> = [7, 7, 7, 1, 7]
+        .iter()
+        .map(|d| overlapping.feed(*d))
+        .collect();
+    assert_eq!(hits, vec![false, true, true, false, false]);
+    assert!(SequenceMatcher::new(&[]).is_err());
+}
+
+/// Returns the number of recipes to the left of the first occurrence
+/// of `target`, or `None` if it does not appear among the first
+/// `limit` recipes.
+fn recipes_before(target: &[u8], limit: usize) -> Result<Option<usize>, Fail> {
+    let mut board = Scoreboard::with_capacity(limit);
+    let mut matcher = SequenceMatcher::new(target)?;
+    // The initial scores count too.
+    for (i, d) in board.scores.iter().enumerate() {
+        if matcher.feed(*d) {
+            return Ok(Some(i + 1 - target.len()));
+        }
+    }
+    while board.len() < limit {
+        let end = board.len();
+        // A step can append two digits, and the target might end at
+        // the first of them.
+        for (i, d) in board.step().iter().enumerate() {
+            if matcher.feed(*d) {
+                return Ok(Some(end + i + 1 - target.len()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[test]
+fn test_part1() {
+    assert_eq!(scores_after(9), "5158916779");
+    assert_eq!(scores_after(5), "0124515891");
+    assert_eq!(scores_after(18), "9251071085");
+    assert_eq!(scores_after(2018), "5941429882");
+}
+
+#[test]
+fn test_part2() {
+    let find = |s: &str| {
+        recipes_before(&parse_digits(s).expect("valid digits"), 10_000).expect("non-empty target")
+    };
+    assert_eq!(find("51589"), Some(9));
+    assert_eq!(find("01245"), Some(5));
+    assert_eq!(find("92510"), Some(18));
+    assert_eq!(find("59414"), Some(2018));
+    // In the initial scores.
+    assert_eq!(find("37"), Some(0));
+    assert_eq!(find("7"), Some(1));
+    // Ends at the first of the two digits made by the first step.
+    assert_eq!(find("371"), Some(0));
+    assert_eq!(find("3710"), Some(0));
+    assert_eq!(find("1010"), Some(2));
+    assert_eq!(find("999999999"), None);
+    assert!(recipes_before(&[], 10_000).is_err());
+}
+
+#[test]
+fn test_parse_digits() {
+    assert_eq!(parse_digits("0123\n").expect("valid"), vec![0, 1, 2, 3]);
+    assert!(parse_digits("12a").is_err());
+    assert!(parse_digits("\n").is_err());
+}
+
+fn main() {
+    let m = Command::new("Advent of code 2018 day 14")
+        .about("Solves the day 14 puzzle")
+        .arg(
+            Arg::new("max-recipes")
+                .long("max-recipes")
+                .takes_value(true)
+                .default_value("100000000")
+                .help("give up on part 2 after making this many recipes"),
+        )
+        .get_matches();
+    let limit: usize = m
+        .value_of("max-recipes")
+        .expect("has a default")
+        .parse()
+        .expect("--max-recipes should be a number");
+    let text =
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8");
+    let digits = parse_digits(text).expect("valid input");
+    let count: usize = text.trim().parse().expect("input should be a number");
+    println!("Day 14 part 1: {}", scores_after(count));
+    match recipes_before(&digits, limit).expect("valid input") {
+        Some(n) => println!("Day 14 part 2: {n}"),
+        None => println!("Day 14 part 2: not found in the first {limit} recipes"),
+    }
+}
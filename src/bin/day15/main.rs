@@ -0,0 +1,493 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::str;
+
+use lib::error::Fail;
+use lib::grid::{CompassDirection, Grid, Position};
+
+const INITIAL_HIT_POINTS: i32 = 200;
+const GOBLIN_ATTACK_POWER: i32 = 3;
+
+/// Adjacent squares, in reading order.
+const NEIGHBOURS: [CompassDirection; 4] = [
+    CompassDirection::North,
+    CompassDirection::West,
+    CompassDirection::East,
+    CompassDirection::South,
+];
+
+fn neighbours(p: Position) -> impl Iterator<Item = Position> {
+    NEIGHBOURS.iter().map(move |d| p.move_direction(d))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Elf,
+    Goblin,
+}
+
+impl From<Kind> for char {
+    fn from(k: Kind) -> char {
+        match k {
+            Kind::Elf => 'E',
+            Kind::Goblin => 'G',
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Unit {
+    kind: Kind,
+    position: Position,
+    hit_points: i32,
+    attack_power: i32,
+}
+
+impl Unit {
+    fn is_alive(&self) -> bool {
+        self.hit_points > 0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Outcome {
+    full_rounds: u32,
+    winner: Kind,
+    remaining_hit_points: i32,
+}
+
+impl Outcome {
+    fn score(&self) -> i64 {
+        i64::from(self.full_rounds) * i64::from(self.remaining_hit_points)
+    }
+}
+
+/// What happened during a unit's turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Turn {
+    /// There was nobody left to fight, so the combat is over.
+    NoEnemies,
+    Done,
+    /// The unit killed an elf.
+    ElfKilled,
+}
+
+#[derive(Debug, Clone)]
+struct Combat {
+    /// True for open floor, false for walls.
+    open: Grid<bool>,
+    units: Vec<Unit>,
+    /// The index in `units` of the living unit on each square.
+    occupant: Grid<Option<usize>>,
+    full_rounds: u32,
+}
+
+impl Combat {
+    fn parse(text: &str) -> Result<Combat, Fail> {
+        let mut units: Vec<Unit> = Vec::new();
+        let open = Grid::from_text(text, false, |position, ch| {
+            let kind = match ch {
+                '#' => {
+                    return Ok(false);
+                }
+                '.' => {
+                    return Ok(true);
+                }
+                'E' => Kind::Elf,
+                'G' => Kind::Goblin,
+                _ => {
+                    return Err(Fail(format!("unexpected character '{ch}' at {position}")));
+                }
+            };
+            units.push(Unit {
+                kind,
+                position,
+                hit_points: INITIAL_HIT_POINTS,
+                attack_power: GOBLIN_ATTACK_POWER,
+            });
+            Ok(true)
+        })?;
+        let mut occupant = Grid::new(open.width(), open.height(), None);
+        for (i, unit) in units.iter().enumerate() {
+            if let Some(cell) = occupant.get_mut(&unit.position) {
+                *cell = Some(i);
+            }
+        }
+        Ok(Combat {
+            open,
+            units,
+            occupant,
+            full_rounds: 0,
+        })
+    }
+
+    fn set_elf_attack_power(&mut self, power: i32) {
+        for unit in self.units.iter_mut().filter(|u| u.kind == Kind::Elf) {
+            unit.attack_power = power;
+        }
+    }
+
+    fn is_free(&self, p: &Position) -> bool {
+        self.open.get(p) == Some(&true) && self.occupant.get(p) == Some(&None)
+    }
+
+    /// Returns the number of steps from `start` to every square
+    /// reachable from it through free squares.
+    fn distances_from(&self, start: Position) -> Grid<Option<u32>> {
+        let mut distance: Grid<Option<u32>> =
+            Grid::new(self.open.width(), self.open.height(), None);
+        let mut queue: VecDeque<(Position, u32)> = VecDeque::new();
+        if let Some(d) = distance.get_mut(&start) {
+            *d = Some(0);
+            queue.push_back((start, 0));
+        }
+        while let Some((p, d)) = queue.pop_front() {
+            for n in neighbours(p) {
+                if !self.is_free(&n) {
+                    continue;
+                }
+                if let Some(slot @ None) = distance.get_mut(&n) {
+                    *slot = Some(d + 1);
+                    queue.push_back((n, d + 1));
+                }
+            }
+        }
+        distance
+    }
+
+    fn is_enemy_of(&self, i: usize, j: usize) -> bool {
+        i != j && self.units[j].is_alive() && self.units[j].kind != self.units[i].kind
+    }
+
+    fn adjacent_enemies(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        neighbours(self.units[i].position)
+            .filter_map(|p| self.occupant.get(&p).copied().flatten())
+            .filter(move |j| self.is_enemy_of(i, *j))
+    }
+
+    /// Works out where unit `i` should step to get closer to the
+    /// nearest square in range of an enemy, if it can reach one.
+    fn next_step(&self, i: usize) -> Option<Position> {
+        let from_unit = self.distances_from(self.units[i].position);
+        let destination = (0..self.units.len())
+            .filter(|j| self.is_enemy_of(i, *j))
+            .flat_map(|j| neighbours(self.units[j].position))
+            .filter_map(|p| from_unit.get(&p).copied().flatten().map(|d| (d, p)))
+            .min_by_key(|(d, p)| (*d, p.reading_order()))
+            .map(|(_, p)| p)?;
+        let from_destination = self.distances_from(destination);
+        neighbours(self.units[i].position)
+            .filter_map(|p| from_destination.get(&p).copied().flatten().map(|d| (d, p)))
+            .min_by_key(|(d, p)| (*d, p.reading_order()))
+            .map(|(_, p)| p)
+    }
+
+    /// Gives unit `i` its turn.
+    fn take_turn(&mut self, i: usize) -> Turn {
+        if !(0..self.units.len()).any(|j| self.is_enemy_of(i, j)) {
+            return Turn::NoEnemies;
+        }
+        if self.adjacent_enemies(i).next().is_none() {
+            if let Some(step) = self.next_step(i) {
+                let from = self.units[i].position;
+                if let Some(cell) = self.occupant.get_mut(&from) {
+                    *cell = None;
+                }
+                if let Some(cell) = self.occupant.get_mut(&step) {
+                    *cell = Some(i);
+                }
+                self.units[i].position = step;
+            }
+        }
+        let target = self.adjacent_enemies(i).min_by_key(|j| {
+            let u = &self.units[*j];
+            (u.hit_points, u.position.reading_order())
+        });
+        if let Some(j) = target {
+            self.units[j].hit_points -= self.units[i].attack_power;
+            if !self.units[j].is_alive() {
+                if let Some(cell) = self.occupant.get_mut(&self.units[j].position) {
+                    *cell = None;
+                }
+                if self.units[j].kind == Kind::Elf {
+                    return Turn::ElfKilled;
+                }
+            }
+        }
+        Turn::Done
+    }
+
+    /// Plays one round.  Returns false if the combat ended during the
+    /// round, or an elf died and `elves_may_die` is false.
+    fn round(&mut self, elves_may_die: bool) -> bool {
+        let mut order: Vec<usize> = (0..self.units.len())
+            .filter(|i| self.units[*i].is_alive())
+            .collect();
+        order.sort_by_key(|i| self.units[*i].position.reading_order());
+        for i in order {
+            // Units can be killed before their turn comes.
+            if !self.units[i].is_alive() {
+                continue;
+            }
+            match self.take_turn(i) {
+                Turn::NoEnemies => return false,
+                Turn::ElfKilled if !elves_may_die => return false,
+                Turn::ElfKilled | Turn::Done => (),
+            }
+        }
+        self.full_rounds += 1;
+        true
+    }
+
+    /// Fights to the end, or returns `None` as soon as an elf dies if
+    /// `elves_may_die` is false.
+    fn fight(mut self, elves_may_die: bool) -> Option<Outcome> {
+        while self.round(elves_may_die) {}
+        let survivors: Vec<&Unit> = self.units.iter().filter(|u| u.is_alive()).collect();
+        let winner = survivors.first()?.kind;
+        if survivors.iter().any(|u| u.kind != winner) {
+            // We stopped early because an elf died.
+            return None;
+        }
+        if !elves_may_die
+            && self
+                .units
+                .iter()
+                .any(|u| u.kind == Kind::Elf && !u.is_alive())
+        {
+            return None;
+        }
+        Some(Outcome {
+            full_rounds: self.full_rounds,
+            winner,
+            remaining_hit_points: survivors.iter().map(|u| u.hit_points).sum(),
+        })
+    }
+}
+
+impl Display for Combat {
+    /// Draws the map as the puzzle does, with the hit points of the
+    /// units in each row at the end of the row.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for y in 0..self.open.height() as i64 {
+            let mut row_units: Vec<&Unit> = Vec::new();
+            for x in 0..self.open.width() as i64 {
+                let p = Position { x, y };
+                let ch = match self.occupant.get(&p).copied().flatten() {
+                    Some(i) => {
+                        row_units.push(&self.units[i]);
+                        char::from(self.units[i].kind)
+                    }
+                    None if self.open.get(&p) == Some(&true) => '.',
+                    None => '#',
+                };
+                write!(f, "{ch}")?;
+            }
+            for (n, unit) in row_units.iter().enumerate() {
+                let separator = if n == 0 { "   " } else { ", " };
+                write!(
+                    f,
+                    "{separator}{}({})",
+                    char::from(unit.kind),
+                    unit.hit_points
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn solve_part1(combat: &Combat) -> Result<Outcome, Fail> {
+    combat
+        .clone()
+        .fight(true)
+        .ok_or_else(|| Fail("there was nobody left to fight".to_string()))
+}
+
+/// Finds the smallest elf attack power with which the elves win
+/// without losing anyone, and the outcome of that fight.
+fn solve_part2(combat: &Combat) -> Result<(i32, Outcome), Fail> {
+    // With an attack power of 200 each elf kills with every blow, so
+    // searching further would be pointless.
+    for power in (GOBLIN_ATTACK_POWER + 1)..=INITIAL_HIT_POINTS {
+        let mut trial = combat.clone();
+        trial.set_elf_attack_power(power);
+        if let Some(outcome) = trial.fight(false) {
+            return Ok((power, outcome));
+        }
+    }
+    Err(Fail(
+        "the elves cannot win without losses at any attack power".to_string(),
+    ))
+}
+
+/// A worked example from the puzzle.  The part 1 result is the
+/// number of full rounds, the remaining hit points and the score; for
+/// part 2 the elves' attack power comes first.
+#[cfg(test)]
+struct Example {
+    map: &'static str,
+    part1: (u32, i32, i64),
+    part2: Option<(i32, u32, i32, i64)>,
+}
+
+#[cfg(test)]
+const EXAMPLES: [Example; 6] = [
+    Example {
+        map: "#######\n#.G...#\n#...EG#\n#.#.#G#\n#..G#E#\n#.....#\n#######\n",
+        part1: (47, 590, 27730),
+        part2: Some((15, 29, 172, 4988)),
+    },
+    Example {
+        map: "#######\n#G..#E#\n#E#E.E#\n#G.##.#\n#...#E#\n#...E.#\n#######\n",
+        part1: (37, 982, 36334),
+        part2: None,
+    },
+    Example {
+        map: "#######\n#E..EG#\n#.#G.E#\n#E.##E#\n#G..#.#\n#..E#.#\n#######\n",
+        part1: (46, 859, 39514),
+        part2: Some((4, 33, 948, 31284)),
+    },
+    Example {
+        map: "#######\n#E.G#.#\n#.#G..#\n#G.#.G#\n#G..#.#\n#...E.#\n#######\n",
+        part1: (35, 793, 27755),
+        part2: Some((15, 37, 94, 3478)),
+    },
+    Example {
+        map: "#######\n#.E...#\n#.#..G#\n#.###.#\n#E#G#G#\n#...#G#\n#######\n",
+        part1: (54, 536, 28944),
+        part2: Some((12, 39, 166, 6474)),
+    },
+    Example {
+        map: concat!(
+            "#########\n#G......#\n#.E.#...#\n#..##..G#\n#...##..#\n",
+            "#...#...#\n#.G...G.#\n#.....G.#\n#########\n",
+        ),
+        part1: (20, 937, 18740),
+        part2: Some((34, 30, 38, 1140)),
+    },
+];
+
+#[test]
+fn test_parse() {
+    let combat = Combat::parse(EXAMPLES[0].map).expect("valid example");
+    assert_eq!(combat.units.len(), 6);
+    assert_eq!(combat.units[1].kind, Kind::Elf);
+    assert_eq!(combat.units[1].position, Position { x: 4, y: 2 });
+    assert_eq!(
+        combat.to_string(),
+        concat!(
+            "#######\n",
+            "#.G...#   G(200)\n",
+            "#...EG#   E(200), G(200)\n",
+            "#.#.#G#   G(200)\n",
+            "#..G#E#   G(200), E(200)\n",
+            "#.....#\n",
+            "#######\n",
+        )
+    );
+    assert!(Combat::parse("#.X#\n").is_err());
+}
+
+#[test]
+fn test_movement() {
+    let mut combat = Combat::parse(concat!(
+        "#########\n#G..G..G#\n#.......#\n#.......#\n#G..E..G#\n",
+        "#.......#\n#.......#\n#G..G..G#\n#########\n",
+    ))
+    .expect("valid example");
+    for _ in 0..3 {
+        assert!(combat.round(true));
+    }
+    let map: Vec<String> = combat
+        .to_string()
+        .lines()
+        .map(|line| line.split("   ").next().unwrap_or("").to_string())
+        .collect();
+    assert_eq!(
+        map,
+        vec![
+            "#########",
+            "#.......#",
+            "#..GGG..#",
+            "#..GEG..#",
+            "#G..G...#",
+            "#......G#",
+            "#.......#",
+            "#.......#",
+            "#########",
+        ]
+    );
+}
+
+#[test]
+fn test_first_round_of_combat() {
+    let mut combat = Combat::parse(EXAMPLES[0].map).expect("valid example");
+    assert!(combat.round(true));
+    assert_eq!(
+        combat.to_string(),
+        concat!(
+            "#######\n",
+            "#..G..#   G(200)\n",
+            "#...EG#   E(197), G(197)\n",
+            "#.#G#G#   G(200), G(197)\n",
+            "#...#E#   E(197)\n",
+            "#.....#\n",
+            "#######\n",
+        )
+    );
+}
+
+#[test]
+fn test_part1() {
+    for Example {
+        map,
+        part1: (rounds, hit_points, score),
+        ..
+    } in EXAMPLES.iter()
+    {
+        let outcome = solve_part1(&Combat::parse(map).expect("valid example")).expect("a winner");
+        assert_eq!(
+            (
+                outcome.full_rounds,
+                outcome.remaining_hit_points,
+                outcome.score()
+            ),
+            (*rounds, *hit_points, *score),
+            "{map}"
+        );
+    }
+}
+
+#[test]
+fn test_part2() {
+    for Example { map, part2, .. } in EXAMPLES.iter() {
+        if let Some((power, rounds, hit_points, score)) = part2 {
+            let (p, outcome) =
+                solve_part2(&Combat::parse(map).expect("valid example")).expect("elves win");
+            assert_eq!(outcome.winner, Kind::Elf);
+            assert_eq!(
+                (
+                    p,
+                    outcome.full_rounds,
+                    outcome.remaining_hit_points,
+                    outcome.score()
+                ),
+                (*power, *rounds, *hit_points, *score),
+                "{map}"
+            );
+        }
+    }
+}
+
+fn main() {
+    let combat = Combat::parse(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    let outcome = solve_part1(&combat).expect("the combat should have a winner");
+    println!("Day 15 part 1: {}", outcome.score());
+    let (_, outcome) = solve_part2(&combat).expect("the elves should be able to win");
+    println!("Day 15 part 2: {}", outcome.score());
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+use std::str;
+
+use lib::elfcode::{deduce_opcodes, Halt, Instruction, Machine, Opcode, Program, Sample, Word};
+use lib::error::Fail;
+
+fn parse_numbers(text: &str, separator: char) -> Result<Vec<Word>, Fail> {
+    text.split(separator)
+        .map(|n| {
+            n.trim()
+                .parse::<Word>()
+                .map_err(|e| Fail(format!("invalid number '{n}': {e}")))
+        })
+        .collect()
+}
+
+fn parse_registers(line: &str, label: &str) -> Result<Vec<Word>, Fail> {
+    let registers = line
+        .strip_prefix(label)
+        .map(|rest| rest.trim())
+        .and_then(|rest| rest.strip_prefix('['))
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or_else(|| Fail(format!("expected '{label} [...]', got '{line}'")))?;
+    parse_numbers(registers, ',')
+}
+
+fn parse_raw_instruction(line: &str) -> Result<[Word; 4], Fail> {
+    let numbers = parse_numbers(line.trim(), ' ')?;
+    numbers
+        .try_into()
+        .map_err(|_| Fail(format!("expected four numbers, got '{line}'")))
+}
+
+/// Parses the puzzle input, which is a list of samples followed by a
+/// program whose opcodes are given as numbers.
+fn parse_input(text: &str) -> Result<(Vec<Sample>, Vec<[Word; 4]>), Fail> {
+    let mut lines = text.lines().map(|line| line.trim()).peekable();
+    let mut samples: Vec<Sample> = Vec::new();
+    loop {
+        while lines.peek() == Some(&"") {
+            lines.next();
+        }
+        match lines.peek() {
+            Some(line) if line.starts_with("Before:") => (),
+            _ => break,
+        }
+        let mut next = |what: &str| {
+            lines.next().ok_or_else(|| {
+                Fail(format!(
+                    "sample {} is missing its {what}",
+                    samples.len() + 1
+                ))
+            })
+        };
+        let before = parse_registers(next("'Before' line")?, "Before:")?;
+        let instruction = parse_raw_instruction(next("instruction")?)?;
+        let after = parse_registers(next("'After' line")?, "After:")?;
+        samples.push(Sample {
+            before,
+            instruction,
+            after,
+        });
+    }
+    let program = lines
+        .filter(|line| !line.is_empty())
+        .map(parse_raw_instruction)
+        .collect::<Result<Vec<[Word; 4]>, Fail>>()?;
+    Ok((samples, program))
+}
+
+fn count_ambiguous_samples(samples: &[Sample]) -> usize {
+    samples
+        .iter()
+        .filter(|s| s.matching_opcodes().len() >= 3)
+        .count()
+}
+
+fn decode_program(raw: &[[Word; 4]], opcodes: &BTreeMap<Word, Opcode>) -> Result<Program, Fail> {
+    let instructions = raw
+        .iter()
+        .map(|[number, a, b, c]| match opcodes.get(number) {
+            Some(opcode) => Ok(Instruction {
+                opcode: *opcode,
+                a: *a,
+                b: *b,
+                c: *c,
+            }),
+            None => Err(Fail(format!("opcode number {number} is unknown"))),
+        })
+        .collect::<Result<Vec<Instruction>, Fail>>()?;
+    Ok(Program {
+        ip_register: None,
+        instructions,
+    })
+}
+
+fn solve_part2(samples: &[Sample], raw: &[[Word; 4]]) -> Result<Word, Fail> {
+    let opcodes = deduce_opcodes(samples)?;
+    let mut machine = Machine::new(decode_program(raw, &opcodes)?, 4);
+    match machine.run(None, &mut ())? {
+        Halt::Finished => Ok(machine.registers()[0]),
+        other => Err(Fail(format!("program did not finish: {other:?}"))),
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = concat!(
+    "Before: [3, 2, 1, 1]\n",
+    "9 2 1 2\n",
+    "After:  [3, 2, 2, 1]\n",
+    "\n",
+    "Before: [0, 1, 2, 3]\n",
+    "4 1 3 0\n",
+    "After:  [4, 1, 2, 3]\n",
+    "\n",
+    "\n",
+    "\n",
+    "9 3 3 0\n",
+    "4 0 2 1\n",
+);
+
+#[test]
+fn test_parse_input() {
+    let (samples, program) = parse_input(EXAMPLE).expect("valid example");
+    assert_eq!(samples.len(), 2);
+    assert_eq!(
+        samples[0],
+        Sample {
+            before: vec![3, 2, 1, 1],
+            instruction: [9, 2, 1, 2],
+            after: vec![3, 2, 2, 1],
+        }
+    );
+    assert_eq!(program, vec![[9, 3, 3, 0], [4, 0, 2, 1]]);
+    assert!(parse_input("Before: [3, 2, 1, 1]\n9 2 1 2\n").is_err());
+    assert!(parse_input("Before: [3, 2, 1, 1]\n9 2 1\nAfter:  [3, 2, 2, 1]\n").is_err());
+    assert!(parse_input("Before: 3, 2, 1, 1\n9 2 1 2\nAfter:  [3, 2, 2, 1]\n").is_err());
+}
+
+#[test]
+fn test_part1() {
+    let (samples, _) = parse_input(EXAMPLE).expect("valid example");
+    assert_eq!(count_ambiguous_samples(&samples), 1);
+}
+
+#[test]
+fn test_decode_program() {
+    let (_, raw) = parse_input(EXAMPLE).expect("valid example");
+    let opcodes = BTreeMap::from([(9, Opcode::Seti), (4, Opcode::Eqir)]);
+    let program = decode_program(&raw, &opcodes).expect("all opcodes known");
+    assert_eq!(program.to_string(), "seti 3 3 0\neqir 0 2 1\n");
+    assert!(decode_program(&raw, &BTreeMap::from([(9, Opcode::Seti)])).is_err());
+}
+
+fn main() {
+    let (samples, program) = parse_input(
+        str::from_utf8(include_bytes!("input.txt")).expect("input file should be valid UTF-8"),
+    )
+    .expect("valid input");
+    println!("Day 16 part 1: {}", count_ambiguous_samples(&samples));
+    println!(
+        "Day 16 part 2: {}",
+        solve_part2(&samples, &program).expect("program should run")
+    );
+}
@@ -0,0 +1,343 @@
+//! One-dimensional cellular automata on an unbounded tape.
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::Fail;
+
+/// The rule table of an automaton.  The state of each cell in the
+/// next generation depends on the current state of the cell and the
+/// `radius` cells either side of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    radius: usize,
+    // Indexed by the neighbourhood, read as a binary number with the
+    // leftmost cell as the most significant bit.
+    live: Vec<bool>,
+}
+
+/// The largest neighbourhood radius we accept.  The rule table has an
+/// entry for every neighbourhood, so it doubles in size with each
+/// extra cell.
+const MAX_RADIUS: usize = 5;
+
+fn cell_is_live(ch: char) -> Result<bool, Fail> {
+    match ch {
+        '#' => Ok(true),
+        '.' => Ok(false),
+        _ => Err(Fail(format!(
+            "'{ch}' is not a valid cell (expected '#' or '.')"
+        ))),
+    }
+}
+
+impl Rules {
+    /// Parses rules written one per line, like `##.#. => #`.  Any
+    /// neighbourhood which has no rule leads to a dead cell.  All the
+    /// rules must have the same odd width, and a neighbourhood of only
+    /// dead cells may not lead to a live one, since that would light up
+    /// the whole of the infinite tape.  Errors give line numbers
+    /// counting the first line of `text` as `first_line`.
+    pub fn parse(text: &str, first_line: usize) -> Result<Rules, Fail> {
+        let mut width: Option<usize> = None;
+        let mut outcomes: HashMap<usize, bool> = HashMap::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let bad_line = |why: String| Fail(format!("line {} '{line}': {why}", first_line + n));
+            let (pattern, result) = line
+                .split_once(" => ")
+                .ok_or_else(|| bad_line("expected 'PATTERN => RESULT'".to_string()))?;
+            let pattern_width = pattern.chars().count();
+            match width {
+                None if pattern_width.is_multiple_of(2) => {
+                    return Err(bad_line(format!(
+                        "pattern width {pattern_width} should be odd"
+                    )));
+                }
+                None if pattern_width > 2 * MAX_RADIUS + 1 => {
+                    return Err(bad_line(format!(
+                        "pattern width {pattern_width} is more than the maximum of {}",
+                        2 * MAX_RADIUS + 1
+                    )));
+                }
+                None => width = Some(pattern_width),
+                Some(w) if w != pattern_width => {
+                    return Err(bad_line(format!(
+                        "pattern width {pattern_width} differs from earlier width {w}"
+                    )));
+                }
+                Some(_) => (),
+            }
+            let mut index = 0;
+            for ch in pattern.chars() {
+                index = (index << 1) | usize::from(cell_is_live(ch).map_err(|e| bad_line(e.0))?);
+            }
+            let mut result = result.chars();
+            let outcome = match (result.next(), result.next()) {
+                (Some(ch), None) => cell_is_live(ch).map_err(|e| bad_line(e.0))?,
+                _ => {
+                    return Err(bad_line("result should be a single cell".to_string()));
+                }
+            };
+            if index == 0 && outcome {
+                return Err(bad_line(
+                    "empty neighbourhoods may not produce a live cell".to_string(),
+                ));
+            }
+            if outcomes
+                .insert(index, outcome)
+                .is_some_and(|o| o != outcome)
+            {
+                return Err(bad_line("conflicts with an earlier rule".to_string()));
+            }
+        }
+        let width = width.ok_or_else(|| Fail("there are no rules".to_string()))?;
+        let mut live = vec![false; 1 << width];
+        for (index, outcome) in outcomes {
+            live[index] = outcome;
+        }
+        Ok(Rules {
+            radius: width / 2,
+            live,
+        })
+    }
+
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+}
+
+/// An automaton together with its current state.  Only the live
+/// cells are stored, so the tape extends as far as needed in both
+/// directions.
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    rules: Rules,
+    live: BTreeSet<i64>,
+    generation: u64,
+}
+
+impl Automaton {
+    /// Creates an automaton whose tape holds `initial` (written with
+    /// '#' for live cells and '.' for dead ones) starting at cell 0.
+    pub fn new(rules: Rules, initial: &str) -> Result<Automaton, Fail> {
+        let mut live = BTreeSet::new();
+        for (i, ch) in initial.trim().chars().enumerate() {
+            if cell_is_live(ch)? {
+                live.insert(i as i64);
+            }
+        }
+        Ok(Automaton {
+            rules,
+            live,
+            generation: 0,
+        })
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the positions of the live cells, in ascending order.
+    pub fn live_cells(&self) -> impl Iterator<Item = i64> + '_ {
+        self.live.iter().copied()
+    }
+
+    pub fn step(&mut self) {
+        let (first, last) = match (self.live.first(), self.live.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => {
+                self.generation += 1;
+                return;
+            }
+        };
+        let r = self.rules.radius as i64;
+        let full_mask = self.rules.live.len() - 1;
+        let mut next = BTreeSet::new();
+        // The neighbourhood of the cell `x`, sliding along the tape.
+        // Everything to the left of the first live cell is dead.
+        let mut window: usize = 0;
+        for x in (first - r)..=(last + r) {
+            window = ((window << 1) | usize::from(self.live.contains(&(x + r)))) & full_mask;
+            if self.rules.live[window] {
+                next.insert(x);
+            }
+        }
+        self.live = next;
+        self.generation += 1;
+    }
+
+    /// Runs the automaton until it reaches `target`.  Once the pattern
+    /// of live cells recurs (perhaps shifted along the tape), the rest
+    /// of the run is computed by extrapolation rather than simulation.
+    pub fn advance_to(&mut self, target: u64) {
+        // The generation at which each pattern was seen, and where its
+        // first cell was then.
+        let mut seen: HashMap<Vec<i64>, (u64, i64)> = HashMap::new();
+        while self.generation < target {
+            let offset = self.live.first().copied().unwrap_or(0);
+            let pattern: Vec<i64> = self.live.iter().map(|x| x - offset).collect();
+            if let Some((previous, previous_offset)) =
+                seen.insert(pattern, (self.generation, offset))
+            {
+                let period = self.generation - previous;
+                let drift = offset - previous_offset;
+                let cycles = (target - self.generation) / period;
+                let shift = drift * cycles as i64;
+                self.live = self.live.iter().map(|x| x + shift).collect();
+                self.generation += cycles * period;
+                // Any remaining generations are fewer than one period.
+                while self.generation < target {
+                    self.step();
+                }
+                return;
+            }
+            self.step();
+        }
+    }
+}
+
+impl Display for Automaton {
+    /// Shows the live part of the tape, starting at the first live
+    /// cell.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let (Some(first), Some(last)) = (self.live.first(), self.live.last()) {
+            for x in *first..=*last {
+                f.write_str(if self.live.contains(&x) { "#" } else { "." })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+const RULE_30: &str =
+    "### => .\n##. => .\n#.# => .\n#.. => #\n.## => #\n.#. => #\n..# => #\n... => .\n";
+
+#[test]
+fn test_parse_rules() {
+    let rules = Rules::parse(RULE_30, 1).expect("valid rules");
+    assert_eq!(rules.radius(), 1);
+    assert_eq!(
+        rules.live,
+        vec![false, true, true, true, true, false, false, false]
+    );
+    let wide = Rules::parse("..#.. => #\n\n##.## => .\n", 1).expect("valid rules");
+    assert_eq!(wide.radius(), 2);
+    assert_eq!(wide.live.iter().filter(|l| **l).count(), 1);
+}
+
+#[test]
+fn test_parse_rules_errors() {
+    let error_of = |text: &str| match Rules::parse(text, 1) {
+        Err(Fail(msg)) => msg,
+        Ok(rules) => panic!("expected an error, got {rules:?}"),
+    };
+    assert_eq!(
+        error_of("###=> ."),
+        "line 1 '###=> .': expected 'PATTERN => RESULT'"
+    );
+    assert_eq!(error_of(""), "there are no rules");
+    match Rules::parse("#.# => #\n## => #", 5) {
+        Err(Fail(msg)) => assert_eq!(
+            msg,
+            "line 6 '## => #': pattern width 2 differs from earlier width 3"
+        ),
+        Ok(rules) => panic!("expected an error, got {rules:?}"),
+    }
+    assert_eq!(
+        error_of("## => #"),
+        "line 1 '## => #': pattern width 2 should be odd"
+    );
+    assert_eq!(
+        error_of(&format!("{} => #", "#".repeat(65))),
+        format!(
+            "line 1 '{} => #': pattern width 65 is more than the maximum of 11",
+            "#".repeat(65)
+        )
+    );
+    assert!(Rules::parse(&format!("{} => #", "#".repeat(11)), 1).is_ok());
+    assert_eq!(
+        error_of("#.# => #\n#.#.# => #"),
+        "line 2 '#.#.# => #': pattern width 5 differs from earlier width 3"
+    );
+    assert_eq!(
+        error_of("#x# => #"),
+        "line 1 '#x# => #': 'x' is not a valid cell (expected '#' or '.')"
+    );
+    assert_eq!(
+        error_of("#.# => ##"),
+        "line 1 '#.# => ##': result should be a single cell"
+    );
+    assert_eq!(
+        error_of("... => #"),
+        "line 1 '... => #': empty neighbourhoods may not produce a live cell"
+    );
+    assert_eq!(
+        error_of("#.# => #\n#.# => ."),
+        "line 2 '#.# => .': conflicts with an earlier rule"
+    );
+    // Repeating a rule is harmless.
+    assert!(Rules::parse("#.# => #\n#.# => #", 1).is_ok());
+}
+
+#[test]
+fn test_step() {
+    let rules = Rules::parse(RULE_30, 1).expect("valid rules");
+    let mut automaton = Automaton::new(rules, "#").expect("valid tape");
+    let mut rows: Vec<String> = Vec::new();
+    for _ in 0..4 {
+        rows.push(automaton.to_string());
+        automaton.step();
+    }
+    assert_eq!(rows, vec!["#", "###", "##..#", "##.####"]);
+    assert_eq!(automaton.generation(), 4);
+    assert_eq!(
+        automaton.live_cells().collect::<Vec<i64>>(),
+        vec![-4, -3, 0, 4]
+    );
+
+    let rules = Rules::parse("#.. => #", 1).expect("valid rules");
+    let mut empty = Automaton::new(rules, "...").expect("valid tape");
+    empty.step();
+    assert_eq!(empty.generation(), 1);
+    assert_eq!(empty.to_string(), "");
+}
+
+#[test]
+fn test_advance_to() {
+    // A glider which moves one cell right each generation.
+    let rules = Rules::parse("#.. => #", 1).expect("valid rules");
+    let mut glider = Automaton::new(rules, ".#...#").expect("valid tape");
+    glider.advance_to(50_000_000_000);
+    assert_eq!(glider.generation(), 50_000_000_000);
+    assert_eq!(
+        glider.live_cells().collect::<Vec<i64>>(),
+        vec![50_000_000_001, 50_000_000_005]
+    );
+
+    // Compare extrapolation with plain simulation, for a pattern
+    // which settles down after a while.
+    let rules = Rules::parse(
+        "#.#.. => #\n.#... => #\n..##. => #\n#.##. => #\n...#. => #\n",
+        1,
+    )
+    .expect("valid rules");
+    let initial = "##.#..###.#";
+    for target in [0, 1, 5, 37, 100, 257] {
+        let mut fast = Automaton::new(rules.clone(), initial).expect("valid tape");
+        fast.advance_to(target);
+        let mut slow = Automaton::new(rules.clone(), initial).expect("valid tape");
+        while slow.generation() < target {
+            slow.step();
+        }
+        assert_eq!(fast.generation(), target);
+        assert_eq!(
+            fast.live_cells().collect::<Vec<i64>>(),
+            slow.live_cells().collect::<Vec<i64>>(),
+            "generation {target}"
+        );
+    }
+}
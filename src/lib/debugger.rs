@@ -0,0 +1,635 @@
+//! A debugger for ElfCode programs.
+//!
+//! The debugger drives a `Machine`, stopping it at breakpoints (before
+//! the instruction at an address is executed) and at watchpoints
+//! (after an instruction changes a watched register).  It also counts
+//! how many times each instruction has been executed, so that the hot
+//! loops of a program can be found.
+//!
+//! It is driven by text commands, either interactively (see
+//! `Debugger::interact`) or from a script (see `Debugger::run_script`),
+//! which is how it is tested.
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead, Write};
+
+use crate::disasm::disassemble;
+use crate::elfcode::{Control, ElfCodeError, Halt, Hook, Machine, Word};
+use crate::error::Fail;
+
+/// Why the debugger handed control back to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The next instruction is at a breakpoint.
+    Breakpoint(usize),
+    /// The instruction at `address` changed a watched register.
+    Watchpoint {
+        register: usize,
+        address: usize,
+        old: Word,
+        new: Word,
+    },
+    /// The requested number of instructions was executed.
+    Paused,
+    /// The instruction pointer left the program.
+    Finished,
+}
+
+impl Display for Stop {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(address) => write!(f, "breakpoint at address {address}"),
+            Stop::Watchpoint {
+                register,
+                address,
+                old,
+                new,
+            } => write!(
+                f,
+                "r{register} changed from {old} to {new} at address {address}"
+            ),
+            Stop::Paused => f.write_str("paused"),
+            Stop::Finished => f.write_str("program finished"),
+        }
+    }
+}
+
+/// What the caller should do after a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// Show this text to the user.
+    Output(String),
+    /// The user has finished.
+    Quit,
+}
+
+const HELP: &str = "\
+break ADDRESS      stop before executing the instruction at ADDRESS
+delete ADDRESS     remove a breakpoint
+watch REGISTER     stop when an instruction changes REGISTER
+unwatch REGISTER   remove a watchpoint
+step [COUNT]       execute COUNT instructions (default 1)
+continue [LIMIT]   run until stopped, or for at most LIMIT instructions
+registers          show the instruction pointer and registers
+set REGISTER VALUE change a register (or the instruction pointer, 'ip')
+profile [COUNT]    show the COUNT most executed instructions
+list               disassemble the program
+quit               stop debugging";
+
+/// Stops the machine at breakpoints and watchpoints, and counts the
+/// executions of each instruction.
+struct Monitor<'a> {
+    breakpoints: &'a BTreeSet<usize>,
+    watchpoints: &'a BTreeSet<usize>,
+    profile: &'a mut [u64],
+    // The registers before the current instruction, if any are watched.
+    previous: Vec<Word>,
+    // The breakpoint we last stopped at, if we are still there.  We
+    // don't stop there again before executing its instruction.
+    resuming_from: Option<usize>,
+    stop: Option<Stop>,
+}
+
+impl Hook for Monitor<'_> {
+    fn before(&mut self, machine: &Machine, address: usize) -> Control {
+        let resuming_from = self.resuming_from.take();
+        if self.breakpoints.contains(&address) && resuming_from != Some(address) {
+            self.stop = Some(Stop::Breakpoint(address));
+            return Control::Stop;
+        }
+        if !self.watchpoints.is_empty() {
+            self.previous.clear();
+            self.previous.extend_from_slice(machine.registers());
+        }
+        Control::Continue
+    }
+
+    fn after(&mut self, machine: &Machine, address: usize) -> Control {
+        self.profile[address] += 1;
+        for register in self.watchpoints.iter().copied() {
+            let (old, new) = (self.previous[register], machine.registers()[register]);
+            if old != new {
+                self.stop = Some(Stop::Watchpoint {
+                    register,
+                    address,
+                    old,
+                    new,
+                });
+                return Control::Stop;
+            }
+        }
+        Control::Continue
+    }
+}
+
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    profile: Vec<u64>,
+    last_stop: Option<Stop>,
+}
+
+impl Debugger {
+    pub fn new(machine: Machine) -> Debugger {
+        let profile = vec![0; machine.program().instructions.len()];
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            profile,
+            last_stop: None,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    /// Gives mutable access to the machine.  The caller may move the
+    /// instruction pointer, so the next `resume` stops at a breakpoint
+    /// under it even if that is where the machine last stopped.
+    pub fn machine_mut(&mut self) -> &mut Machine {
+        self.last_stop = None;
+        &mut self.machine
+    }
+
+    /// Returns the number of times the instruction at each address has
+    /// been executed.
+    pub fn profile(&self) -> &[u64] {
+        &self.profile
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) -> Result<(), Fail> {
+        if address >= self.profile.len() {
+            return Err(Fail(format!(
+                "address {address} is outside the program, which has {} instructions",
+                self.profile.len()
+            )));
+        }
+        self.breakpoints.insert(address);
+        Ok(())
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) -> Result<(), Fail> {
+        if self.breakpoints.remove(&address) {
+            Ok(())
+        } else {
+            Err(Fail(format!("there is no breakpoint at address {address}")))
+        }
+    }
+
+    pub fn add_watchpoint(&mut self, register: usize) -> Result<(), Fail> {
+        self.check_register(register)?;
+        self.watchpoints.insert(register);
+        Ok(())
+    }
+
+    pub fn remove_watchpoint(&mut self, register: usize) -> Result<(), Fail> {
+        if self.watchpoints.remove(&register) {
+            Ok(())
+        } else {
+            Err(Fail(format!("register {register} is not being watched")))
+        }
+    }
+
+    fn check_register(&self, register: usize) -> Result<(), Fail> {
+        let count = self.machine.registers().len();
+        if register < count {
+            Ok(())
+        } else {
+            Err(Fail(format!(
+                "there is no register {register}; the machine has {count}"
+            )))
+        }
+    }
+
+    /// Runs the machine until something stops it, or (if `limit` is
+    /// given) that many instructions have been executed.
+    pub fn resume(&mut self, limit: Option<u64>) -> Result<Stop, ElfCodeError> {
+        let mut monitor = Monitor {
+            breakpoints: &self.breakpoints,
+            watchpoints: &self.watchpoints,
+            profile: &mut self.profile,
+            previous: Vec::new(),
+            resuming_from: match self.last_stop {
+                Some(Stop::Breakpoint(address)) if self.machine.address() == Some(address) => {
+                    Some(address)
+                }
+                _ => None,
+            },
+            stop: None,
+        };
+        let stop = match self.machine.run(limit, &mut monitor)? {
+            Halt::Finished => Stop::Finished,
+            Halt::StepLimit => Stop::Paused,
+            Halt::Stopped => monitor.stop.expect("the monitor says why it stopped"),
+        };
+        self.last_stop = Some(stop);
+        Ok(stop)
+    }
+
+    /// Describes where the machine is.
+    pub fn status(&self) -> String {
+        let next = match self.machine.address() {
+            Some(address) => self.machine.program().instructions[address].to_string(),
+            None => "halted".to_string(),
+        };
+        format!(
+            "ip {}  {:?}  {next}",
+            self.machine.ip(),
+            self.machine.registers()
+        )
+    }
+
+    /// Lists the `top` most executed instructions, most executed first.
+    pub fn profile_report(&self, top: usize) -> String {
+        let listing = disassemble(self.machine.program());
+        let lines: Vec<&str> = listing
+            .lines()
+            .filter(|line| !line.starts_with("#ip"))
+            .collect();
+        let mut hot: Vec<(usize, u64)> = self
+            .profile
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        hot.sort_by(|(a1, n1), (a2, n2)| n2.cmp(n1).then(a1.cmp(a2)));
+        let mut out = format!("{:>10}  {} executions in total", "", self.machine.steps());
+        for (address, count) in hot.into_iter().take(top) {
+            out.push_str(&format!("\n{count:>10}  {}", lines[address]));
+        }
+        out
+    }
+
+    /// Carries out one command.
+    pub fn command(&mut self, line: &str) -> Result<Response, Fail> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (&name, args) = match words.split_first() {
+            Some(split) => split,
+            None => {
+                return Ok(Response::Output(String::new()));
+            }
+        };
+        let arg = |n: usize, what: &str| -> Result<&str, Fail> {
+            args.get(n)
+                .copied()
+                .ok_or_else(|| Fail(format!("{name}: expected {what}")))
+        };
+        let number = |text: &str| -> Result<u64, Fail> {
+            text.parse()
+                .map_err(|e| Fail(format!("{name}: invalid number '{text}': {e}")))
+        };
+        let address = |n: usize| -> Result<usize, Fail> {
+            let text = arg(n, "an address")?;
+            text.parse()
+                .map_err(|e| Fail(format!("{name}: invalid address '{text}': {e}")))
+        };
+        let register = |n: usize| -> Result<usize, Fail> {
+            let text = arg(n, "a register")?;
+            let digits = text.strip_prefix('r').unwrap_or(text);
+            digits
+                .parse()
+                .map_err(|e| Fail(format!("{name}: invalid register '{text}': {e}")))
+        };
+        let output = match name {
+            "break" | "b" => {
+                let address = address(0)?;
+                self.add_breakpoint(address)?;
+                format!("breakpoint set at address {address}")
+            }
+            "delete" | "d" => {
+                self.remove_breakpoint(address(0)?)?;
+                String::new()
+            }
+            "watch" | "w" => {
+                let register = register(0)?;
+                self.add_watchpoint(register)?;
+                format!("watching r{register}")
+            }
+            "unwatch" => {
+                self.remove_watchpoint(register(0)?)?;
+                String::new()
+            }
+            "step" | "s" | "continue" | "c" => {
+                let limit = match args.first() {
+                    Some(text) => Some(number(text)?),
+                    None if name.starts_with('s') => Some(1),
+                    None => None,
+                };
+                let stop = self.resume(limit)?;
+                format!("{stop}\n{}", self.status())
+            }
+            "registers" | "r" => self.status(),
+            "set" => {
+                let value = arg(1, "a value")?;
+                let value: Word = value
+                    .parse()
+                    .map_err(|e| Fail(format!("{name}: invalid value '{value}': {e}")))?;
+                if arg(0, "a register")? == "ip" {
+                    // A jump, even to where we stopped, should stop at
+                    // any breakpoint there.
+                    self.machine.set_ip(value);
+                    self.last_stop = None;
+                } else {
+                    let register = register(0)?;
+                    self.check_register(register)?;
+                    self.machine.registers_mut()[register] = value;
+                }
+                self.status()
+            }
+            "profile" | "p" => {
+                let top = match args.first() {
+                    Some(text) => number(text)? as usize,
+                    None => usize::MAX,
+                };
+                self.profile_report(top)
+            }
+            "list" | "l" => disassemble(self.machine.program()),
+            "help" | "h" => HELP.to_string(),
+            "quit" | "q" => {
+                return Ok(Response::Quit);
+            }
+            _ => {
+                return Err(Fail(format!("unknown command '{name}'; try 'help'")));
+            }
+        };
+        Ok(Response::Output(output))
+    }
+
+    /// Carries out each line of `script` as a command, stopping at the
+    /// first which fails.  Returns a transcript showing each command
+    /// (after a prompt) and its output.
+    pub fn run_script(&mut self, script: &str) -> Result<String, Fail> {
+        let mut transcript = String::new();
+        for line in script.lines() {
+            transcript.push_str(&format!("(elf) {}\n", line.trim()));
+            match self.command(line)? {
+                Response::Output(text) if text.is_empty() => (),
+                Response::Output(text) => {
+                    transcript.push_str(text.trim_end());
+                    transcript.push('\n');
+                }
+                Response::Quit => break,
+            }
+        }
+        Ok(transcript)
+    }
+
+    /// Reads commands from `input` until it runs out or the user
+    /// quits.  Errors are reported, but don't end the session.
+    pub fn interact<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        writeln!(output, "{}", self.status())?;
+        write!(output, "(elf) ")?;
+        output.flush()?;
+        for line in input.lines() {
+            match self.command(&line?) {
+                Ok(Response::Output(text)) if text.is_empty() => (),
+                Ok(Response::Output(text)) => writeln!(output, "{}", text.trim_end())?,
+                Ok(Response::Quit) => {
+                    return Ok(());
+                }
+                Err(e) => writeln!(output, "error: {e}")?,
+            }
+            write!(output, "(elf) ")?;
+            output.flush()?;
+        }
+        writeln!(output)
+    }
+}
+
+#[cfg(test)]
+fn divisors_debugger() -> Debugger {
+    use crate::elfcode::Program;
+    let program: Program = crate::disasm::DIVISORS.parse().expect("valid program");
+    Debugger::new(Machine::new(program, 6))
+}
+
+#[test]
+fn test_breakpoints_and_watchpoints() {
+    let mut debugger = divisors_debugger();
+    debugger.add_breakpoint(7).expect("valid address");
+    debugger.add_watchpoint(0).expect("valid register");
+    // The first divisor found is 1, when r2 reaches 10.
+    assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(7)));
+    assert_eq!(debugger.machine().registers()[1..4], [1, 10, 10]);
+    // The breakpoint doesn't stop us again straight away.
+    assert_eq!(
+        debugger.resume(None),
+        Ok(Stop::Watchpoint {
+            register: 0,
+            address: 7,
+            old: 0,
+            new: 1
+        })
+    );
+    assert_eq!(debugger.resume(None), Ok(Stop::Breakpoint(7)));
+    assert_eq!(debugger.machine().registers()[1], 2);
+    debugger.remove_breakpoint(7).expect("breakpoint was set");
+    assert!(debugger.remove_breakpoint(7).is_err());
+    debugger.remove_watchpoint(0).expect("watchpoint was set");
+    assert_eq!(debugger.resume(Some(3)), Ok(Stop::Paused));
+    assert_eq!(debugger.resume(None), Ok(Stop::Finished));
+    assert_eq!(debugger.machine().registers()[0], 18);
+    assert_eq!(debugger.resume(None), Ok(Stop::Finished));
+
+    assert!(debugger.add_breakpoint(17).is_err());
+    assert!(debugger.add_watchpoint(6).is_err());
+}
+
+#[test]
+fn test_profile() {
+    let mut debugger = divisors_debugger();
+    assert_eq!(debugger.resume(None), Ok(Stop::Finished));
+    let profile = debugger.profile();
+    assert_eq!(profile.iter().sum::<u64>(), debugger.machine().steps());
+    // The inner loop runs 10 * 10 times; four of those find a divisor.
+    assert_eq!(profile[3], 100);
+    assert_eq!(profile[7], 4);
+    assert_eq!(profile[16], 1);
+    assert_eq!(
+        debugger.profile_report(2),
+        concat!(
+            "            842 executions in total\n",
+            "       100  L3:      3  mulr 1 2 5      r5 = r1 * r2\n",
+            "       100           4  eqrr 5 3 5      if (r5 != r3) goto L8",
+        )
+    );
+}
+
+#[test]
+fn test_script() {
+    let mut debugger = divisors_debugger();
+    let transcript = debugger
+        .run_script(concat!(
+            "break 12\n",
+            "continue\n",
+            "set r3 3\n",
+            "watch r0\n",
+            "step 2\n",
+            "continue\n",
+            "delete 12\n",
+            "c\n",
+            "unwatch 0\n",
+            "c\n",
+            "quit\n",
+            "this is not run\n",
+        ))
+        .expect("valid script");
+    assert_eq!(
+        transcript,
+        concat!(
+            "(elf) break 12\n",
+            "breakpoint set at address 12\n",
+            "(elf) continue\n",
+            "breakpoint at address 12\n",
+            "ip 12  [1, 1, 11, 10, 11, 1]  addi 1 1 1\n",
+            "(elf) set r3 3\n",
+            "ip 12  [1, 1, 11, 3, 11, 1]  addi 1 1 1\n",
+            "(elf) watch r0\n",
+            "watching r0\n",
+            "(elf) step 2\n",
+            "paused\n",
+            "ip 14  [1, 2, 11, 3, 13, 0]  addr 5 4 4\n",
+            "(elf) continue\n",
+            "breakpoint at address 12\n",
+            "ip 12  [1, 2, 4, 3, 11, 1]  addi 1 1 1\n",
+            "(elf) delete 12\n",
+            "(elf) c\n",
+            "r0 changed from 1 to 4 at address 7\n",
+            "ip 8  [4, 3, 1, 3, 7, 1]  addi 2 1 2\n",
+            "(elf) unwatch 0\n",
+            "(elf) c\n",
+            "program finished\n",
+            "ip 257  [4, 4, 4, 3, 256, 1]  halted\n",
+            "(elf) quit\n",
+        )
+    );
+}
+
+#[test]
+fn test_breakpoint_reached_without_running() {
+    // Only a breakpoint we stopped at is passed over when resuming; one
+    // we arrived at by stepping or by moving the instruction pointer
+    // still stops us.
+    let mut debugger = divisors_debugger();
+    let transcript = debugger
+        .run_script(concat!(
+            "break 1\n",
+            "break 7\n",
+            "step\n",
+            "continue\n",
+            "set ip 7\n",
+            "continue\n",
+            "continue\n",
+        ))
+        .expect("valid script");
+    assert_eq!(
+        transcript,
+        concat!(
+            "(elf) break 1\n",
+            "breakpoint set at address 1\n",
+            "(elf) break 7\n",
+            "breakpoint set at address 7\n",
+            "(elf) step\n",
+            "paused\n",
+            "ip 1  [0, 0, 0, 10, 0, 0]  seti 1 0 1\n",
+            "(elf) continue\n",
+            "breakpoint at address 1\n",
+            "ip 1  [0, 0, 0, 10, 0, 0]  seti 1 0 1\n",
+            "(elf) set ip 7\n",
+            "ip 7  [0, 0, 0, 10, 0, 0]  addr 1 0 0\n",
+            "(elf) continue\n",
+            "breakpoint at address 7\n",
+            "ip 7  [0, 0, 0, 10, 0, 0]  addr 1 0 0\n",
+            "(elf) continue\n",
+            "breakpoint at address 7\n",
+            "ip 7  [0, 1, 10, 10, 6, 1]  addr 1 0 0\n",
+        )
+    );
+}
+
+#[test]
+fn test_jump_back_to_breakpoint() {
+    let mut debugger = divisors_debugger();
+    let transcript = debugger
+        .run_script(concat!(
+            "break 12\n",
+            "continue\n",
+            "set ip 12\n",
+            "continue\n",
+            "step\n",
+            "set ip 12\n",
+            "continue\n",
+        ))
+        .expect("valid script");
+    assert_eq!(
+        transcript,
+        concat!(
+            "(elf) break 12\n",
+            "breakpoint set at address 12\n",
+            "(elf) continue\n",
+            "breakpoint at address 12\n",
+            "ip 12  [1, 1, 11, 10, 11, 1]  addi 1 1 1\n",
+            "(elf) set ip 12\n",
+            "ip 12  [1, 1, 11, 10, 11, 1]  addi 1 1 1\n",
+            "(elf) continue\n",
+            "breakpoint at address 12\n",
+            "ip 12  [1, 1, 11, 10, 11, 1]  addi 1 1 1\n",
+            "(elf) step\n",
+            "paused\n",
+            "ip 13  [1, 2, 11, 10, 12, 1]  gtrr 1 3 5\n",
+            "(elf) set ip 12\n",
+            "ip 12  [1, 2, 11, 10, 12, 1]  addi 1 1 1\n",
+            "(elf) continue\n",
+            "breakpoint at address 12\n",
+            "ip 12  [1, 2, 11, 10, 12, 1]  addi 1 1 1\n",
+        )
+    );
+    // Changing the machine directly may also move it back onto the
+    // breakpoint.
+    let registers = debugger.machine().registers().to_vec();
+    debugger.machine_mut();
+    assert_eq!(debugger.resume(None).expect("runs"), Stop::Breakpoint(12));
+    assert_eq!(debugger.machine().registers(), registers.as_slice());
+}
+
+#[test]
+fn test_script_errors() {
+    let mut debugger = divisors_debugger();
+    assert!(debugger.run_script("frobnicate\n").is_err());
+    assert!(debugger.run_script("break\n").is_err());
+    assert!(debugger.run_script("break x\n").is_err());
+    assert!(debugger.run_script("watch r9\n").is_err());
+    assert!(debugger.run_script("set r1\n").is_err());
+    assert!(debugger.run_script("set r9 1\n").is_err());
+    assert!(debugger.run_script("unwatch 1\n").is_err());
+    assert_eq!(
+        debugger.run_script("\n").expect("blank lines are allowed"),
+        "(elf) \n"
+    );
+}
+
+#[test]
+fn test_interact() {
+    let mut debugger = divisors_debugger();
+    let mut output: Vec<u8> = Vec::new();
+    debugger
+        .interact("step\nbogus\nset ip 16\nstep\n".as_bytes(), &mut output)
+        .expect("writing to a Vec succeeds");
+    assert_eq!(
+        String::from_utf8(output).expect("valid UTF-8"),
+        concat!(
+            "ip 0  [0, 0, 0, 0, 0, 0]  seti 10 0 3\n",
+            "(elf) paused\n",
+            "ip 1  [0, 0, 0, 10, 0, 0]  seti 1 0 1\n",
+            "(elf) error: unknown command 'bogus'; try 'help'\n",
+            "(elf) ip 16  [0, 0, 0, 10, 0, 0]  mulr 4 4 4\n",
+            "(elf) program finished\n",
+            "ip 257  [0, 0, 0, 10, 256, 0]  halted\n",
+            "(elf) \n",
+        )
+    );
+}
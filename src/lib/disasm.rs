@@ -0,0 +1,728 @@
+//! A disassembler and decompiler for ElfCode programs.
+//!
+//! Each instruction (or short run of instructions forming a common
+//! idiom) is decoded into a statement.  Reads of the register bound to
+//! the instruction pointer become constants, and writes to it become
+//! jumps.  A comparison followed by `addr` of its result into the
+//! instruction pointer is a conditional jump; the flag register it
+//! sets is not shown, since programs use it only as a temporary.
+//!
+//! `pseudo_code` goes further and turns backward jumps into loops and
+//! forward conditional jumps into `if` blocks where it can do so
+//! without changing the meaning of the program.  Computed jumps (whose
+//! targets depend on register values) are shown but not followed.
+use std::collections::BTreeSet;
+use std::fmt::{self, Display, Formatter};
+
+use crate::elfcode::{Instruction, Opcode, Operand, Program, Word};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Mul,
+    And,
+    Or,
+    Gt,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl BinOp {
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Mul => "*",
+            BinOp::And => "&",
+            BinOp::Or => "|",
+            BinOp::Gt => ">",
+            BinOp::Le => "<=",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+        }
+    }
+
+    fn is_comparison(&self) -> bool {
+        matches!(self, BinOp::Gt | BinOp::Le | BinOp::Eq | BinOp::Ne)
+    }
+
+    fn apply(&self, a: Word, b: Word) -> Word {
+        match self {
+            BinOp::Add => a.wrapping_add(b),
+            BinOp::Mul => a.wrapping_mul(b),
+            BinOp::And => a & b,
+            BinOp::Or => a | b,
+            BinOp::Gt => Word::from(a > b),
+            BinOp::Le => Word::from(a <= b),
+            BinOp::Eq => Word::from(a == b),
+            BinOp::Ne => Word::from(a != b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Reg(usize),
+    Const(Word),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn binary(op: BinOp, left: Expr, right: Expr) -> Expr {
+        match (&left, &right) {
+            (Expr::Const(a), Expr::Const(b)) => Expr::Const(op.apply(*a, *b)),
+            _ => Expr::Binary(op, Box::new(left), Box::new(right)),
+        }
+    }
+
+    /// Returns `self + n`, folding `n` into any constant term.
+    fn plus(self, n: Word) -> Expr {
+        match self {
+            Expr::Const(k) => Expr::Const(k.wrapping_add(n)),
+            Expr::Binary(BinOp::Add, left, right) => match (*left, *right) {
+                (other, Expr::Const(k)) | (Expr::Const(k), other) => {
+                    Expr::binary(BinOp::Add, other, Expr::Const(k.wrapping_add(n)))
+                }
+                (left, right) => Expr::binary(
+                    BinOp::Add,
+                    Expr::binary(BinOp::Add, left, right),
+                    Expr::Const(n),
+                ),
+            },
+            other => Expr::binary(BinOp::Add, other, Expr::Const(n)),
+        }
+    }
+
+    fn negated(self) -> Expr {
+        match self {
+            Expr::Binary(op, left, right) if op.is_comparison() => {
+                let op = match op {
+                    BinOp::Gt => BinOp::Le,
+                    BinOp::Le => BinOp::Gt,
+                    BinOp::Eq => BinOp::Ne,
+                    _ => BinOp::Eq,
+                };
+                Expr::Binary(op, left, right)
+            }
+            other => Expr::binary(BinOp::Eq, other, Expr::Const(0)),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Reg(r) => write!(f, "r{r}"),
+            Expr::Const(k) => write!(f, "{k}"),
+            Expr::Binary(op, left, right) => {
+                for (n, side) in [left, right].into_iter().enumerate() {
+                    if n > 0 {
+                        write!(f, " {} ", op.symbol())?;
+                    }
+                    match side.as_ref() {
+                        Expr::Binary(..) => write!(f, "({side})")?,
+                        _ => write!(f, "{side}")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Where a jump goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Address(usize),
+    /// Outside the program, so the program stops.
+    Halt,
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Address(a) => write!(f, "goto L{a}"),
+            Target::Halt => f.write_str("halt"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stmt {
+    Assign {
+        register: usize,
+        value: Expr,
+    },
+    Goto(Target),
+    /// A jump to the address given by the expression.
+    ComputedGoto(Expr),
+    IfGoto {
+        condition: Expr,
+        target: Target,
+    },
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Stmt::Assign {
+                register,
+                value: Expr::Binary(op, left, right),
+            } if op.is_comparison() => write!(
+                f,
+                "r{register} = ({})",
+                Expr::Binary(*op, left.clone(), right.clone())
+            ),
+            Stmt::Assign {
+                register,
+                value: Expr::Binary(op, left, right),
+            } if **left == Expr::Reg(*register) => {
+                write!(f, "r{register} {}= {right}", op.symbol())
+            }
+            // The remaining operators are all commutative.
+            Stmt::Assign {
+                register,
+                value: Expr::Binary(op, left, right),
+            } if **right == Expr::Reg(*register) => {
+                write!(f, "r{register} {}= {left}", op.symbol())
+            }
+            Stmt::Assign { register, value } => write!(f, "r{register} = {value}"),
+            Stmt::Goto(target) => write!(f, "{target}"),
+            Stmt::ComputedGoto(expr) => write!(f, "goto ({expr})"),
+            Stmt::IfGoto { condition, target } => write!(f, "if ({condition}) {target}"),
+        }
+    }
+}
+
+/// A statement decoded from `length` instructions starting at
+/// `address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub length: usize,
+    pub stmt: Stmt,
+}
+
+impl Line {
+    fn target(&self) -> Option<usize> {
+        match self.stmt {
+            Stmt::Goto(Target::Address(a))
+            | Stmt::IfGoto {
+                target: Target::Address(a),
+                ..
+            } => Some(a),
+            _ => None,
+        }
+    }
+}
+
+fn binop(opcode: Opcode) -> Option<BinOp> {
+    use Opcode::*;
+    match opcode {
+        Addr | Addi => Some(BinOp::Add),
+        Mulr | Muli => Some(BinOp::Mul),
+        Banr | Bani => Some(BinOp::And),
+        Borr | Bori => Some(BinOp::Or),
+        Gtir | Gtri | Gtrr => Some(BinOp::Gt),
+        Eqir | Eqri | Eqrr => Some(BinOp::Eq),
+        Setr | Seti => None,
+    }
+}
+
+fn target_of(next: Word, program_length: usize) -> Target {
+    match usize::try_from(next) {
+        Ok(a) if a < program_length => Target::Address(a),
+        _ => Target::Halt,
+    }
+}
+
+/// Works out what the instruction at `address` computes, as seen by
+/// a program with the instruction pointer bound to `ip`.
+fn value_of(insn: &Instruction, address: usize, ip: Option<usize>) -> Expr {
+    let operand = |kind: Operand, x: Word| match kind {
+        Operand::Register if ip.is_some_and(|ip| ip as Word == x) => Expr::Const(address as Word),
+        Operand::Register => Expr::Reg(x as usize),
+        Operand::Immediate | Operand::Ignored => Expr::Const(x),
+    };
+    let (kind_a, kind_b) = insn.opcode.operands();
+    let a = operand(kind_a, insn.a);
+    match binop(insn.opcode) {
+        Some(op) => Expr::binary(op, a, operand(kind_b, insn.b)),
+        None => a,
+    }
+}
+
+fn decode_one(program: &Program, address: usize) -> Stmt {
+    let insn = &program.instructions[address];
+    let value = value_of(insn, address, program.ip_register);
+    if program.ip_register.is_some_and(|ip| ip as Word == insn.c) {
+        // The instruction pointer is incremented after the write.
+        match value.plus(1) {
+            Expr::Const(next) => Stmt::Goto(target_of(next, program.instructions.len())),
+            next => Stmt::ComputedGoto(next),
+        }
+    } else {
+        Stmt::Assign {
+            register: insn.c as usize,
+            value,
+        }
+    }
+}
+
+/// Recognises a conditional jump: a comparison into register `x`,
+/// then `addr x ip ip` to skip the next instruction if it was true.
+/// Returns the condition.
+fn conditional_skip(program: &Program, address: usize) -> Option<Expr> {
+    let ip = program.ip_register? as Word;
+    let compare = program.instructions.get(address)?;
+    let add = program.instructions.get(address + 1)?;
+    let is_comparison = binop(compare.opcode).is_some_and(|op| op.is_comparison());
+    let x = compare.c;
+    if is_comparison
+        && x != ip
+        && add.opcode == Opcode::Addr
+        && add.c == ip
+        && ((add.a == x && add.b == ip) || (add.a == ip && add.b == x))
+    {
+        Some(value_of(compare, address, program.ip_register))
+    } else {
+        None
+    }
+}
+
+/// Decodes the program into statements, combining instructions which
+/// form a conditional jump.  Instructions which are the target of a
+/// jump are never combined with the ones before them.
+pub fn decode(program: &Program) -> Vec<Line> {
+    let n = program.instructions.len();
+    let single: Vec<Stmt> = (0..n).map(|a| decode_one(program, a)).collect();
+    let targets: BTreeSet<usize> = single
+        .iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Goto(Target::Address(a)) => Some(*a),
+            _ => None,
+        })
+        .collect();
+    let mut lines: Vec<Line> = Vec::with_capacity(n);
+    let mut address = 0;
+    while address < n {
+        let condition =
+            conditional_skip(program, address).filter(|_| !targets.contains(&(address + 1)));
+        let line = match condition {
+            Some(condition) => match single.get(address + 2) {
+                // If true, skip the jump which follows.
+                Some(Stmt::Goto(target)) if !targets.contains(&(address + 2)) => Line {
+                    address,
+                    length: 3,
+                    stmt: Stmt::IfGoto {
+                        condition: condition.negated(),
+                        target: *target,
+                    },
+                },
+                _ => Line {
+                    address,
+                    length: 2,
+                    stmt: Stmt::IfGoto {
+                        condition,
+                        target: target_of(address as Word + 3, n),
+                    },
+                },
+            },
+            None => Line {
+                address,
+                length: 1,
+                stmt: single[address].clone(),
+            },
+        };
+        address += line.length;
+        lines.push(line);
+    }
+    lines
+}
+
+/// Lists the program one instruction per line, with a label on each
+/// jump target and a description of what each instruction does.  When
+/// several instructions form one statement, the statement is shown
+/// against the first of them.
+pub fn disassemble(program: &Program) -> String {
+    let lines = decode(program);
+    let targets: BTreeSet<usize> = lines.iter().filter_map(|line| line.target()).collect();
+    let mut out = String::new();
+    if let Some(ip) = program.ip_register {
+        out.push_str(&format!("#ip {ip}\n"));
+    }
+    for line in lines.iter() {
+        for address in line.address..line.address + line.length {
+            let label = if targets.contains(&address) {
+                format!("L{address}:")
+            } else {
+                String::new()
+            };
+            let comment = if address == line.address {
+                line.stmt.to_string()
+            } else {
+                "...".to_string()
+            };
+            let insn = program.instructions[address].to_string();
+            out.push_str(&format!("{label:<6}{address:>4}  {insn:<16}{comment}\n"));
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Line(Line),
+    If { condition: Expr, body: Vec<Node> },
+    DoWhile { body: Vec<Node>, condition: Expr },
+    Loop { body: Vec<Node> },
+}
+
+struct Structurer<'a> {
+    lines: &'a [Line],
+    /// The address just past the end of the program.
+    end_address: usize,
+}
+
+impl Structurer<'_> {
+    fn address_of(&self, index: usize) -> usize {
+        self.lines
+            .get(index)
+            .map(|line| line.address)
+            .unwrap_or(self.end_address)
+    }
+
+    /// Returns true if a line outside `lines[first..=last]` jumps to
+    /// an address in `low..high`.
+    fn jumps_into(&self, first: usize, last: usize, low: usize, high: usize) -> bool {
+        self.lines.iter().enumerate().any(|(i, line)| {
+            (i < first || i > last) && line.target().is_some_and(|t| low <= t && t < high)
+        })
+    }
+
+    /// Finds the last line in `lines[start + 1..end]` which jumps back
+    /// to `lines[start]`, such that nothing else jumps into the middle
+    /// of the loop this forms.
+    fn loop_end(&self, start: usize, end: usize) -> Option<usize> {
+        let head = self.address_of(start);
+        ((start + 1)..end).rev().find(|k| {
+            self.lines[*k].target() == Some(head)
+                && !self.jumps_into(start, *k, head + 1, self.address_of(*k + 1))
+        })
+    }
+
+    fn build(&self, start: usize, end: usize) -> Vec<Node> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut i = start;
+        while i < end {
+            if let Some(k) = self.loop_end(i, end) {
+                let body = self.build(i, k);
+                nodes.push(match &self.lines[k].stmt {
+                    Stmt::IfGoto { condition, .. } => Node::DoWhile {
+                        body,
+                        condition: condition.clone(),
+                    },
+                    _ => Node::Loop { body },
+                });
+                i = k + 1;
+                continue;
+            }
+            let line = &self.lines[i];
+            if let Stmt::IfGoto {
+                condition,
+                target: Target::Address(t),
+            } = &line.stmt
+            {
+                // A forward jump over the lines up to `j`.
+                let skipped = ((i + 1)..=end).find(|j| self.address_of(*j) == *t);
+                if let Some(j) = skipped {
+                    if !self.jumps_into(i, j - 1, self.address_of(i + 1), *t) {
+                        nodes.push(Node::If {
+                            condition: condition.clone().negated(),
+                            body: self.build(i + 1, j),
+                        });
+                        i = j;
+                        continue;
+                    }
+                }
+            }
+            nodes.push(Node::Line(line.clone()));
+            i += 1;
+        }
+        nodes
+    }
+}
+
+fn collect_targets(nodes: &[Node], targets: &mut BTreeSet<usize>) {
+    for node in nodes {
+        match node {
+            Node::Line(line) => targets.extend(line.target()),
+            Node::If { body, .. } | Node::DoWhile { body, .. } | Node::Loop { body } => {
+                collect_targets(body, targets)
+            }
+        }
+    }
+}
+
+fn render(nodes: &[Node], depth: usize, labels: &BTreeSet<usize>, out: &mut String) {
+    let indent = "    ".repeat(depth + 1);
+    for node in nodes {
+        match node {
+            Node::Line(line) => {
+                if labels.contains(&line.address) {
+                    out.push_str(&format!("L{}:\n", line.address));
+                }
+                out.push_str(&format!("{indent}{}\n", line.stmt));
+            }
+            Node::If { condition, body } => {
+                out.push_str(&format!("{indent}if ({condition}) {{\n"));
+                render(body, depth + 1, labels, out);
+                out.push_str(&format!("{indent}}}\n"));
+            }
+            Node::DoWhile { body, condition } => {
+                out.push_str(&format!("{indent}do {{\n"));
+                render(body, depth + 1, labels, out);
+                out.push_str(&format!("{indent}}} while ({condition})\n"));
+            }
+            Node::Loop { body } => {
+                out.push_str(&format!("{indent}loop {{\n"));
+                render(body, depth + 1, labels, out);
+                out.push_str(&format!("{indent}}}\n"));
+            }
+        }
+    }
+}
+
+/// Decompiles the program into structured pseudo-code.  Jumps which
+/// could not be turned into loops or `if` blocks are left as `goto`s,
+/// with labels on their targets.
+pub fn pseudo_code(program: &Program) -> String {
+    let lines = decode(program);
+    let structurer = Structurer {
+        lines: &lines,
+        end_address: program.instructions.len(),
+    };
+    let nodes = structurer.build(0, lines.len());
+    let mut labels = BTreeSet::new();
+    collect_targets(&nodes, &mut labels);
+    let mut out = String::new();
+    if let Some(ip) = program.ip_register {
+        out.push_str(&format!("#ip {ip}\n"));
+    }
+    render(&nodes, 0, &labels, &mut out);
+    out
+}
+
+/// Adds up the divisors of 10, leaving the result in register 0.
+#[cfg(test)]
+pub(crate) const DIVISORS: &str = concat!(
+    "#ip 4\n",
+    "seti 10 0 3\n",
+    "seti 1 0 1\n",
+    "seti 1 0 2\n",
+    "mulr 1 2 5\n",
+    "eqrr 5 3 5\n",
+    "addr 5 4 4\n",
+    "addi 4 1 4\n",
+    "addr 1 0 0\n",
+    "addi 2 1 2\n",
+    "gtrr 2 3 5\n",
+    "addr 4 5 4\n",
+    "seti 2 0 4\n",
+    "addi 1 1 1\n",
+    "gtrr 1 3 5\n",
+    "addr 5 4 4\n",
+    "seti 1 0 4\n",
+    "mulr 4 4 4\n",
+);
+
+#[test]
+fn test_divisors_program_runs() {
+    use crate::elfcode::{Halt, Machine};
+    let program: Program = DIVISORS.parse().expect("valid program");
+    let mut machine = Machine::new(program, 6);
+    assert_eq!(machine.run(Some(10_000), &mut ()), Ok(Halt::Finished));
+    assert_eq!(machine.registers()[0], 1 + 2 + 5 + 10);
+}
+
+#[test]
+fn test_decode() {
+    let program: Program = DIVISORS.parse().expect("valid program");
+    let lines = decode(&program);
+    let summary: Vec<(usize, usize, String)> = lines
+        .iter()
+        .map(|line| (line.address, line.length, line.stmt.to_string()))
+        .collect();
+    let expected: Vec<(usize, usize, String)> = [
+        (0, 1, "r3 = 10"),
+        (1, 1, "r1 = 1"),
+        (2, 1, "r2 = 1"),
+        (3, 1, "r5 = r1 * r2"),
+        (4, 3, "if (r5 != r3) goto L8"),
+        (7, 1, "r0 += r1"),
+        (8, 1, "r2 += 1"),
+        (9, 3, "if (r2 <= r3) goto L3"),
+        (12, 1, "r1 += 1"),
+        (13, 3, "if (r1 <= r3) goto L2"),
+        (16, 1, "halt"),
+    ]
+    .iter()
+    .map(|(a, n, s)| (*a, *n, s.to_string()))
+    .collect();
+    assert_eq!(summary, expected);
+}
+
+#[test]
+fn test_disassemble() {
+    let program: Program = DIVISORS.parse().expect("valid program");
+    assert_eq!(
+        disassemble(&program),
+        concat!(
+            "#ip 4\n",
+            "         0  seti 10 0 3     r3 = 10\n",
+            "         1  seti 1 0 1      r1 = 1\n",
+            "L2:      2  seti 1 0 2      r2 = 1\n",
+            "L3:      3  mulr 1 2 5      r5 = r1 * r2\n",
+            "         4  eqrr 5 3 5      if (r5 != r3) goto L8\n",
+            "         5  addr 5 4 4      ...\n",
+            "         6  addi 4 1 4      ...\n",
+            "         7  addr 1 0 0      r0 += r1\n",
+            "L8:      8  addi 2 1 2      r2 += 1\n",
+            "         9  gtrr 2 3 5      if (r2 <= r3) goto L3\n",
+            "        10  addr 4 5 4      ...\n",
+            "        11  seti 2 0 4      ...\n",
+            "        12  addi 1 1 1      r1 += 1\n",
+            "        13  gtrr 1 3 5      if (r1 <= r3) goto L2\n",
+            "        14  addr 5 4 4      ...\n",
+            "        15  seti 1 0 4      ...\n",
+            "        16  mulr 4 4 4      halt\n",
+        )
+    );
+}
+
+#[test]
+fn test_pseudo_code() {
+    let program: Program = DIVISORS.parse().expect("valid program");
+    assert_eq!(
+        pseudo_code(&program),
+        concat!(
+            "#ip 4\n",
+            "    r3 = 10\n",
+            "    r1 = 1\n",
+            "    do {\n",
+            "        r2 = 1\n",
+            "        do {\n",
+            "            r5 = r1 * r2\n",
+            "            if (r5 == r3) {\n",
+            "                r0 += r1\n",
+            "            }\n",
+            "            r2 += 1\n",
+            "        } while (r2 <= r3)\n",
+            "        r1 += 1\n",
+            "    } while (r1 <= r3)\n",
+            "    halt\n",
+        )
+    );
+}
+
+#[test]
+fn test_unstructured_jumps() {
+    // Jumping into the middle of a loop prevents it being shown as
+    // one; the instruction pointer can also be read as a constant, and
+    // used for computed jumps.
+    let program: Program = concat!(
+        "#ip 3\n",
+        "addi 3 2 3\n",
+        "seti 5 0 1\n",
+        "addi 1 1 1\n",
+        "mulr 3 1 2\n",
+        "eqri 1 9 0\n",
+        "addr 0 3 3\n",
+        "seti 1 0 3\n",
+        "addr 3 2 3\n",
+        "seti 99 0 3\n",
+    )
+    .parse()
+    .expect("valid program");
+    assert_eq!(
+        disassemble(&program),
+        concat!(
+            "#ip 3\n",
+            "         0  addi 3 2 3      goto L3\n",
+            "         1  seti 5 0 1      r1 = 5\n",
+            "L2:      2  addi 1 1 1      r1 += 1\n",
+            "L3:      3  mulr 3 1 2      r2 = 3 * r1\n",
+            "         4  eqri 1 9 0      if (r1 != 9) goto L2\n",
+            "         5  addr 0 3 3      ...\n",
+            "         6  seti 1 0 3      ...\n",
+            "         7  addr 3 2 3      goto (r2 + 8)\n",
+            "         8  seti 99 0 3     halt\n",
+        )
+    );
+    assert_eq!(
+        pseudo_code(&program),
+        concat!(
+            "#ip 3\n",
+            "    goto L3\n",
+            "    r1 = 5\n",
+            "L2:\n",
+            "    r1 += 1\n",
+            "L3:\n",
+            "    r2 = 3 * r1\n",
+            "    if (r1 != 9) goto L2\n",
+            "    goto (r2 + 8)\n",
+            "    halt\n",
+        )
+    );
+}
+
+#[test]
+fn test_idioms_without_ip() {
+    // With no #ip, nothing is a jump.
+    let program: Program = "seti 7 0 1\nsetr 1 0 2\naddr 3 3 3\ngtir 4 1 0\n"
+        .parse()
+        .expect("valid program");
+    assert_eq!(
+        pseudo_code(&program),
+        "    r1 = 7\n    r2 = r1\n    r3 += r3\n    r0 = (4 > r1)\n"
+    );
+}
+
+#[test]
+fn test_jump_past_largest_address() {
+    let program: Program = "#ip 0\nseti 9223372036854775807 0 0\naddi 1 9223372036854775807 0\n"
+        .parse()
+        .expect("valid program");
+    assert_eq!(
+        pseudo_code(&program),
+        "#ip 0\n    halt\n    goto (r1 + -9223372036854775808)\n"
+    );
+}
+
+#[test]
+fn test_two_instruction_skip() {
+    // A conditional skip not followed by a jump; the loop back to the
+    // start has no condition.
+    let program: Program = concat!(
+        "#ip 5\n",
+        "addi 0 1 0\n",
+        "gtri 0 3 1\n",
+        "addr 1 5 5\n",
+        "addi 2 1 2\n",
+        "seti -1 0 5\n",
+    )
+    .parse()
+    .expect("valid program");
+    assert_eq!(
+        pseudo_code(&program),
+        concat!(
+            "#ip 5\n",
+            "    loop {\n",
+            "        r0 += 1\n",
+            "        if (r0 <= 3) {\n",
+            "            r2 += 1\n",
+            "        }\n",
+            "    }\n",
+        )
+    );
+}
@@ -0,0 +1,745 @@
+//! The "ElfCode" register machine used by several of the 2018
+//! puzzles (days 16, 19 and 21).
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::Fail;
+
+/// The value held in a register.
+pub type Word = i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Opcode {
+    Addr,
+    Addi,
+    Mulr,
+    Muli,
+    Banr,
+    Bani,
+    Borr,
+    Bori,
+    Setr,
+    Seti,
+    Gtir,
+    Gtri,
+    Gtrr,
+    Eqir,
+    Eqri,
+    Eqrr,
+}
+
+pub const ALL_OPCODES: [Opcode; 16] = [
+    Opcode::Addr,
+    Opcode::Addi,
+    Opcode::Mulr,
+    Opcode::Muli,
+    Opcode::Banr,
+    Opcode::Bani,
+    Opcode::Borr,
+    Opcode::Bori,
+    Opcode::Setr,
+    Opcode::Seti,
+    Opcode::Gtir,
+    Opcode::Gtri,
+    Opcode::Gtrr,
+    Opcode::Eqir,
+    Opcode::Eqri,
+    Opcode::Eqrr,
+];
+
+/// How an instruction uses one of its operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register,
+    Immediate,
+    Ignored,
+}
+
+impl Opcode {
+    pub fn name(&self) -> &'static str {
+        use Opcode::*;
+        match self {
+            Addr => "addr",
+            Addi => "addi",
+            Mulr => "mulr",
+            Muli => "muli",
+            Banr => "banr",
+            Bani => "bani",
+            Borr => "borr",
+            Bori => "bori",
+            Setr => "setr",
+            Seti => "seti",
+            Gtir => "gtir",
+            Gtri => "gtri",
+            Gtrr => "gtrr",
+            Eqir => "eqir",
+            Eqri => "eqri",
+            Eqrr => "eqrr",
+        }
+    }
+
+    /// Returns how the instruction uses its A and B operands.  (C is
+    /// always the register which receives the result.)
+    pub fn operands(&self) -> (Operand, Operand) {
+        use Opcode::*;
+        use Operand::*;
+        match self {
+            Addr | Mulr | Banr | Borr | Gtrr | Eqrr => (Register, Register),
+            Addi | Muli | Bani | Bori | Gtri | Eqri => (Register, Immediate),
+            Gtir | Eqir => (Immediate, Register),
+            Setr => (Register, Ignored),
+            Seti => (Immediate, Ignored),
+        }
+    }
+}
+
+impl Display for Opcode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Opcode {
+    type Err = Fail;
+
+    fn from_str(s: &str) -> Result<Opcode, Fail> {
+        ALL_OPCODES
+            .iter()
+            .find(|op| op.name() == s)
+            .copied()
+            .ok_or_else(|| Fail(format!("unknown opcode '{s}'")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ElfCodeError {
+    /// An instruction referred to a register which does not exist.
+    BadRegister { register: Word, registers: usize },
+}
+
+impl Display for ElfCodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfCodeError::BadRegister {
+                register,
+                registers,
+            } => write!(
+                f,
+                "register {register} does not exist (there are {registers} registers)"
+            ),
+        }
+    }
+}
+
+impl Error for ElfCodeError {}
+
+impl From<ElfCodeError> for Fail {
+    fn from(e: ElfCodeError) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+fn register(registers: &[Word], r: Word) -> Result<usize, ElfCodeError> {
+    usize::try_from(r)
+        .ok()
+        .filter(|r| *r < registers.len())
+        .ok_or(ElfCodeError::BadRegister {
+            register: r,
+            registers: registers.len(),
+        })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub a: Word,
+    pub b: Word,
+    pub c: Word,
+}
+
+impl Instruction {
+    /// Carries out the instruction on `registers`.  If it fails, the
+    /// registers are left unchanged.
+    pub fn execute(&self, registers: &mut [Word]) -> Result<(), ElfCodeError> {
+        let value = |operand: Operand, x: Word| -> Result<Word, ElfCodeError> {
+            match operand {
+                Operand::Register => Ok(registers[register(registers, x)?]),
+                Operand::Immediate => Ok(x),
+                Operand::Ignored => Ok(0),
+            }
+        };
+        let (kind_a, kind_b) = self.opcode.operands();
+        let a = value(kind_a, self.a)?;
+        let b = value(kind_b, self.b)?;
+        let c = register(registers, self.c)?;
+        use Opcode::*;
+        registers[c] = match self.opcode {
+            Addr | Addi => a.wrapping_add(b),
+            Mulr | Muli => a.wrapping_mul(b),
+            Banr | Bani => a & b,
+            Borr | Bori => a | b,
+            Setr | Seti => a,
+            Gtir | Gtri | Gtrr => Word::from(a > b),
+            Eqir | Eqri | Eqrr => Word::from(a == b),
+        };
+        Ok(())
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} {}", self.opcode, self.a, self.b, self.c)
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Fail;
+
+    fn from_str(s: &str) -> Result<Instruction, Fail> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        match fields.as_slice() {
+            [opcode, a, b, c] => {
+                let operand = |text: &str| {
+                    text.parse::<Word>()
+                        .map_err(|e| Fail(format!("invalid operand '{text}': {e}")))
+                };
+                Ok(Instruction {
+                    opcode: opcode.parse()?,
+                    a: operand(a)?,
+                    b: operand(b)?,
+                    c: operand(c)?,
+                })
+            }
+            _ => Err(Fail(format!(
+                "expected an opcode and three operands, got '{s}'"
+            ))),
+        }
+    }
+}
+
+/// A program, optionally with the instruction pointer bound to a
+/// register by an `#ip` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub ip_register: Option<usize>,
+    pub instructions: Vec<Instruction>,
+}
+
+impl FromStr for Program {
+    type Err = Fail;
+
+    fn from_str(text: &str) -> Result<Program, Fail> {
+        let mut ip_register: Option<usize> = None;
+        let mut instructions: Vec<Instruction> = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let in_line = |Fail(msg)| Fail(format!("line {}: {msg}", n + 1));
+            if line.is_empty() {
+                continue;
+            } else if let Some(r) = line.strip_prefix("#ip ") {
+                if ip_register.is_some() || !instructions.is_empty() {
+                    return Err(in_line(Fail(
+                        "#ip must come once, before any instructions".to_string(),
+                    )));
+                }
+                ip_register = Some(r.trim().parse().map_err(|e| {
+                    in_line(Fail(format!("invalid instruction pointer register: {e}")))
+                })?);
+            } else {
+                instructions.push(line.parse().map_err(in_line)?);
+            }
+        }
+        Ok(Program {
+            ip_register,
+            instructions,
+        })
+    }
+}
+
+impl Display for Program {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(r) = self.ip_register {
+            writeln!(f, "#ip {r}")?;
+        }
+        for insn in self.instructions.iter() {
+            writeln!(f, "{insn}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a `Hook` wants the machine to do next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+/// Lets the caller watch (and stop) a running machine.  `before` is
+/// called before each instruction is executed, and `after` once it
+/// has been.  Both are told the address of the instruction.
+pub trait Hook {
+    fn before(&mut self, _machine: &Machine, _address: usize) -> Control {
+        Control::Continue
+    }
+
+    fn after(&mut self, _machine: &Machine, _address: usize) -> Control {
+        Control::Continue
+    }
+}
+
+/// A hook which does nothing.
+impl Hook for () {}
+
+/// Why `Machine::run` returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    /// The instruction pointer left the program.
+    Finished,
+    /// The step limit was reached.
+    StepLimit,
+    /// A hook asked to stop.
+    Stopped,
+}
+
+#[derive(Debug, Clone)]
+pub struct Machine {
+    program: Program,
+    registers: Vec<Word>,
+    ip: Word,
+    steps: u64,
+}
+
+impl Machine {
+    /// Creates a machine with `register_count` registers, all zero.
+    pub fn new(program: Program, register_count: usize) -> Machine {
+        Machine {
+            program,
+            registers: vec![0; register_count],
+            ip: 0,
+            steps: 0,
+        }
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub fn registers(&self) -> &[Word] {
+        &self.registers
+    }
+
+    pub fn registers_mut(&mut self) -> &mut [Word] {
+        &mut self.registers
+    }
+
+    pub fn ip(&self) -> Word {
+        self.ip
+    }
+
+    pub fn set_ip(&mut self, ip: Word) {
+        self.ip = ip;
+    }
+
+    /// Returns the number of instructions executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns the address of the next instruction, or `None` if the
+    /// instruction pointer is outside the program.
+    pub fn address(&self) -> Option<usize> {
+        usize::try_from(self.ip)
+            .ok()
+            .filter(|a| *a < self.program.instructions.len())
+    }
+
+    /// Executes one instruction.  Returns false (without doing
+    /// anything) if the machine has already halted.  If the
+    /// instruction fails, the machine is left unchanged.
+    pub fn step(&mut self) -> Result<bool, ElfCodeError> {
+        let address = match self.address() {
+            Some(a) => a,
+            None => {
+                return Ok(false);
+            }
+        };
+        let insn = self.program.instructions[address];
+        if let Some(r) = self.program.ip_register {
+            let r = register(&self.registers, r as Word)?;
+            let saved = std::mem::replace(&mut self.registers[r], self.ip);
+            if let Err(e) = insn.execute(&mut self.registers) {
+                // Leave the machine as it was.
+                self.registers[r] = saved;
+                return Err(e);
+            }
+        } else {
+            insn.execute(&mut self.registers)?;
+        }
+        if let Some(r) = self.program.ip_register {
+            self.ip = self.registers[r];
+        }
+        // An instruction pointer which can't be incremented has left
+        // the program as surely as one which can; any negative value
+        // will do to show that.
+        self.ip = self.ip.checked_add(1).unwrap_or(Word::MIN);
+        self.steps += 1;
+        Ok(true)
+    }
+
+    /// Runs the program until it finishes, a hook stops it, or
+    /// `limit` more instructions have been executed.
+    pub fn run(&mut self, limit: Option<u64>, hook: &mut dyn Hook) -> Result<Halt, ElfCodeError> {
+        let mut executed: u64 = 0;
+        loop {
+            let address = match self.address() {
+                Some(a) => a,
+                None => {
+                    return Ok(Halt::Finished);
+                }
+            };
+            if limit.is_some_and(|limit| executed >= limit) {
+                return Ok(Halt::StepLimit);
+            }
+            if hook.before(self, address) == Control::Stop {
+                return Ok(Halt::Stopped);
+            }
+            self.step()?;
+            executed += 1;
+            if hook.after(self, address) == Control::Stop {
+                return Ok(Halt::Stopped);
+            }
+        }
+    }
+}
+
+/// An observation of a single instruction, from the day 16 puzzle:
+/// the registers before and after an instruction whose opcode is
+/// given only as a number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub before: Vec<Word>,
+    pub instruction: [Word; 4],
+    pub after: Vec<Word>,
+}
+
+impl Sample {
+    /// Returns the opcodes which behave as the sample shows.
+    pub fn matching_opcodes(&self) -> BTreeSet<Opcode> {
+        let [_, a, b, c] = self.instruction;
+        ALL_OPCODES
+            .iter()
+            .copied()
+            .filter(|opcode| {
+                let mut registers = self.before.clone();
+                let insn = Instruction {
+                    opcode: *opcode,
+                    a,
+                    b,
+                    c,
+                };
+                insn.execute(&mut registers).is_ok() && registers == self.after
+            })
+            .collect()
+    }
+}
+
+/// Works out which opcode each opcode number stands for.  Each
+/// sample narrows down the candidates for its number; then any number
+/// with only one candidate left rules that opcode out for all the
+/// other numbers, and so on until every number is resolved.
+pub fn deduce_opcodes(samples: &[Sample]) -> Result<BTreeMap<Word, Opcode>, Fail> {
+    let mut candidates: BTreeMap<Word, BTreeSet<Opcode>> = BTreeMap::new();
+    for sample in samples {
+        let matching = sample.matching_opcodes();
+        candidates
+            .entry(sample.instruction[0])
+            .and_modify(|c| c.retain(|op| matching.contains(op)))
+            .or_insert(matching);
+    }
+    let mut resolved: BTreeMap<Word, Opcode> = BTreeMap::new();
+    while resolved.len() < candidates.len() {
+        let newly_resolved: Vec<(Word, Opcode)> = candidates
+            .iter()
+            .filter(|(number, _)| !resolved.contains_key(number))
+            .filter_map(|(number, ops)| match ops.len() {
+                1 => ops.first().map(|op| Ok((*number, *op))),
+                0 => Some(Err(Fail(format!(
+                    "no opcode is consistent with the samples for opcode number {number}"
+                )))),
+                _ => None,
+            })
+            .collect::<Result<_, Fail>>()?;
+        if newly_resolved.is_empty() {
+            return Err(Fail(format!(
+                "the samples do not determine the opcodes; still undecided: {}",
+                candidates
+                    .iter()
+                    .filter(|(number, _)| !resolved.contains_key(number))
+                    .map(|(number, ops)| format!(
+                        "{number} ({})",
+                        ops.iter().map(|op| op.name()).collect::<Vec<_>>().join("/")
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
+        }
+        for (number, opcode) in newly_resolved {
+            resolved.insert(number, opcode);
+            for (other, ops) in candidates.iter_mut() {
+                if *other != number {
+                    ops.remove(&opcode);
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+#[test]
+fn test_execute() {
+    let run = |opcode: Opcode, a: Word, b: Word| -> Word {
+        let mut registers = vec![5, 12, 0, 0];
+        Instruction { opcode, a, b, c: 3 }
+            .execute(&mut registers)
+            .expect("valid registers");
+        registers[3]
+    };
+    use Opcode::*;
+    assert_eq!(run(Addr, 0, 1), 17);
+    assert_eq!(run(Addi, 0, 1), 6);
+    assert_eq!(run(Mulr, 0, 1), 60);
+    assert_eq!(run(Muli, 0, 3), 15);
+    assert_eq!(run(Banr, 0, 1), 4);
+    assert_eq!(run(Bani, 1, 6), 4);
+    assert_eq!(run(Borr, 0, 1), 13);
+    assert_eq!(run(Bori, 0, 8), 13);
+    assert_eq!(run(Setr, 1, 99), 12);
+    assert_eq!(run(Seti, 7, 99), 7);
+    assert_eq!(run(Gtir, 6, 0), 1);
+    assert_eq!(run(Gtir, 5, 0), 0);
+    assert_eq!(run(Gtri, 1, 11), 1);
+    assert_eq!(run(Gtrr, 0, 1), 0);
+    assert_eq!(run(Eqir, 5, 0), 1);
+    assert_eq!(run(Eqri, 1, 11), 0);
+    assert_eq!(run(Eqrr, 0, 0), 1);
+}
+
+#[test]
+fn test_execute_bad_register() {
+    let mut registers = vec![1, 2, 3, 4];
+    let insn: Instruction = "addr 0 4 1".parse().expect("valid instruction");
+    assert_eq!(
+        insn.execute(&mut registers),
+        Err(ElfCodeError::BadRegister {
+            register: 4,
+            registers: 4
+        })
+    );
+    assert_eq!(registers, vec![1, 2, 3, 4]);
+    // Immediate operands can be anything, but C is always a register.
+    let insn: Instruction = "seti -4 -9 -1".parse().expect("valid instruction");
+    assert!(insn.execute(&mut registers).is_err());
+}
+
+#[test]
+fn test_parse_program() {
+    let text = "#ip 0\nseti 5 0 1\nseti 6 0 2\naddi 0 1 0\n";
+    let program: Program = text.parse().expect("valid program");
+    assert_eq!(program.ip_register, Some(0));
+    assert_eq!(program.instructions.len(), 3);
+    assert_eq!(
+        program.instructions[2],
+        Instruction {
+            opcode: Opcode::Addi,
+            a: 0,
+            b: 1,
+            c: 0
+        }
+    );
+    assert_eq!(program.to_string(), text);
+
+    let error_of = |text: &str| match text.parse::<Program>() {
+        Err(Fail(msg)) => msg,
+        Ok(p) => panic!("expected an error, got {p:?}"),
+    };
+    assert_eq!(
+        error_of("#ip 0\nmovr 1 2 3"),
+        "line 2: unknown opcode 'movr'"
+    );
+    assert_eq!(
+        error_of("addr 1 2"),
+        "line 1: expected an opcode and three operands, got 'addr 1 2'"
+    );
+    assert_eq!(
+        error_of("seti 1 2 3\n#ip 1"),
+        "line 2: #ip must come once, before any instructions"
+    );
+    assert!(error_of("#ip x").starts_with("line 1: invalid instruction pointer register"));
+    assert!(error_of("seti 1 x 3").starts_with("line 1: invalid operand 'x'"));
+}
+
+#[cfg(test)]
+const DAY19_EXAMPLE: &str = concat!(
+    "#ip 0\n",
+    "seti 5 0 1\n",
+    "seti 6 0 2\n",
+    "addi 0 1 0\n",
+    "addr 1 2 3\n",
+    "setr 1 0 0\n",
+    "seti 8 0 4\n",
+    "seti 9 0 5\n",
+);
+
+#[test]
+fn test_run() {
+    let program: Program = DAY19_EXAMPLE.parse().expect("valid program");
+    let mut machine = Machine::new(program, 6);
+    assert_eq!(machine.run(None, &mut ()), Ok(Halt::Finished));
+    assert_eq!(machine.registers(), &[6, 5, 6, 0, 0, 9]);
+    assert_eq!(machine.steps(), 5);
+    assert_eq!(machine.ip(), 7);
+    assert_eq!(machine.address(), None);
+    assert_eq!(machine.step(), Ok(false));
+}
+
+#[test]
+fn test_run_with_limit_and_hook() {
+    let program: Program = DAY19_EXAMPLE.parse().expect("valid program");
+    let mut machine = Machine::new(program.clone(), 6);
+    assert_eq!(machine.run(Some(3), &mut ()), Ok(Halt::StepLimit));
+    assert_eq!(machine.steps(), 3);
+    assert_eq!(machine.address(), Some(4));
+    assert_eq!(machine.run(Some(3), &mut ()), Ok(Halt::Finished));
+    assert_eq!(machine.steps(), 5);
+
+    // A hook which records the addresses executed, and stops the
+    // machine once register 0 is 5.
+    struct Trace(Vec<usize>);
+    impl Hook for Trace {
+        fn before(&mut self, _machine: &Machine, address: usize) -> Control {
+            self.0.push(address);
+            Control::Continue
+        }
+        fn after(&mut self, machine: &Machine, _address: usize) -> Control {
+            if machine.registers()[0] == 5 {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+    }
+    let mut trace = Trace(Vec::new());
+    let mut machine = Machine::new(program, 6);
+    assert_eq!(machine.run(None, &mut trace), Ok(Halt::Stopped));
+    assert_eq!(trace.0, vec![0, 1, 2, 4]);
+    assert_eq!(machine.ip(), 6);
+}
+
+#[test]
+fn test_ip_overflow_halts() {
+    let program: Program = "#ip 0\nseti 9223372036854775807 0 0\n"
+        .parse()
+        .expect("valid program");
+    let mut machine = Machine::new(program, 1);
+    assert_eq!(machine.run(None, &mut ()), Ok(Halt::Finished));
+    assert_eq!(machine.registers(), &[Word::MAX]);
+    assert_eq!(machine.steps(), 1);
+    assert_eq!(machine.address(), None);
+}
+
+#[test]
+fn test_run_bad_ip_register() {
+    let program: Program = "#ip 6\nseti 1 0 0\n".parse().expect("valid program");
+    let mut machine = Machine::new(program, 6);
+    assert!(machine.run(None, &mut ()).is_err());
+    assert_eq!(machine.steps(), 0);
+}
+
+#[test]
+fn test_failed_step_changes_nothing() {
+    // The instruction pointer is copied into register 1 before each
+    // instruction, but that must be undone if the instruction fails.
+    let program: Program = "#ip 1\nseti 5 0 2\naddr 9 0 0\n"
+        .parse()
+        .expect("valid program");
+    let mut machine = Machine::new(program, 3);
+    assert_eq!(machine.step(), Ok(true));
+    assert_eq!(machine.registers(), &[0, 0, 5]);
+    assert!(machine.step().is_err());
+    assert_eq!(machine.registers(), &[0, 0, 5]);
+    assert_eq!(machine.ip(), 1);
+    assert_eq!(machine.steps(), 1);
+}
+
+#[test]
+fn test_matching_opcodes() {
+    let sample = Sample {
+        before: vec![3, 2, 1, 1],
+        instruction: [9, 2, 1, 2],
+        after: vec![3, 2, 2, 1],
+    };
+    assert_eq!(
+        sample.matching_opcodes(),
+        BTreeSet::from([Opcode::Mulr, Opcode::Addi, Opcode::Seti])
+    );
+}
+
+#[test]
+fn test_deduce_opcodes() {
+    use rand::{Rng, SeedableRng};
+    // Make up a numbering, generate random samples from it, and check
+    // that we can work it out again.
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20181216);
+    let mut numbering: Vec<Opcode> = ALL_OPCODES.to_vec();
+    for i in (1..numbering.len()).rev() {
+        numbering.swap(i, rng.gen_range(0..=i));
+    }
+    let samples: Vec<Sample> = (0..800)
+        .map(|_| {
+            let number = rng.gen_range(0..16);
+            let before: Vec<Word> = (0..4).map(|_| rng.gen_range(0..4)).collect();
+            let (a, b, c) = (
+                rng.gen_range(0..4),
+                rng.gen_range(0..4),
+                rng.gen_range(0..4),
+            );
+            let mut after = before.clone();
+            Instruction {
+                opcode: numbering[number as usize],
+                a,
+                b,
+                c,
+            }
+            .execute(&mut after)
+            .expect("valid registers");
+            Sample {
+                before,
+                instruction: [number, a, b, c],
+                after,
+            }
+        })
+        .collect();
+    let deduced = deduce_opcodes(&samples).expect("enough samples");
+    for (number, opcode) in numbering.iter().enumerate() {
+        assert_eq!(deduced.get(&(number as Word)), Some(opcode));
+    }
+
+    // One sample cannot tell us which of three opcodes it is.
+    let ambiguous = Sample {
+        before: vec![3, 2, 1, 1],
+        instruction: [9, 2, 1, 2],
+        after: vec![3, 2, 2, 1],
+    };
+    match deduce_opcodes(std::slice::from_ref(&ambiguous)) {
+        Err(Fail(msg)) => assert!(msg.ends_with("undecided: 9 (addi/mulr/seti)"), "{msg}"),
+        Ok(m) => panic!("expected an error, got {m:?}"),
+    }
+    let impossible = Sample {
+        after: vec![0, 0, 0, 0],
+        ..ambiguous
+    };
+    assert!(deduce_opcodes(&[impossible]).is_err());
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+
+/// The graph contains a cycle, so its nodes have no topological
+/// order.  The nodes are given in the order they occur around the
+/// cycle, starting from its lowest node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle<N>(pub Vec<N>);
+
+impl<N: Display> Display for Cycle<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("dependency cycle: ")?;
+        for node in self.0.iter() {
+            write!(f, "{node} -> ")?;
+        }
+        match self.0.first() {
+            Some(first) => write!(f, "{first}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<N: Debug + Display> Error for Cycle<N> {}
+
+/// The reason `Graph::schedule` could not schedule the steps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScheduleError<N> {
+    Cycle(Cycle<N>),
+    /// There are steps, but nobody to carry them out.
+    NoWorkers,
+}
+
+impl<N: Display> Display for ScheduleError<N> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Cycle(cycle) => write!(f, "{cycle}"),
+            ScheduleError::NoWorkers => f.write_str("there are steps to do but no workers"),
+        }
+    }
+}
+
+impl<N: Debug + Display> Error for ScheduleError<N> {}
+
+impl<N> From<Cycle<N>> for ScheduleError<N> {
+    fn from(cycle: Cycle<N>) -> ScheduleError<N> {
+        ScheduleError::Cycle(cycle)
+    }
+}
+
+/// A directed graph.  An edge from `a` to `b` means that `a` must
+/// come before `b`.
+#[derive(Debug, Clone)]
+pub struct Graph<N> {
+    successors: BTreeMap<N, BTreeSet<N>>,
+    predecessors: BTreeMap<N, BTreeSet<N>>,
+}
+
+impl<N: Copy + Ord> Default for Graph<N> {
+    fn default() -> Graph<N> {
+        Graph::new()
+    }
+}
+
+impl<N: Copy + Ord> Graph<N> {
+    pub fn new() -> Graph<N> {
+        Graph {
+            successors: BTreeMap::new(),
+            predecessors: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_node(&mut self, node: N) {
+        self.successors.entry(node).or_default();
+        self.predecessors.entry(node).or_default();
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N) {
+        self.add_node(from);
+        self.add_node(to);
+        self.successors.entry(from).or_default().insert(to);
+        self.predecessors.entry(to).or_default().insert(from);
+    }
+
+    /// Returns the nodes in ascending order.
+    pub fn nodes(&self) -> impl Iterator<Item = N> + '_ {
+        self.successors.keys().copied()
+    }
+
+    pub fn successors(&self, node: N) -> impl Iterator<Item = N> + '_ {
+        self.successors.get(&node).into_iter().flatten().copied()
+    }
+
+    pub fn predecessors(&self, node: N) -> impl Iterator<Item = N> + '_ {
+        self.predecessors.get(&node).into_iter().flatten().copied()
+    }
+
+    fn unmet_dependencies(&self) -> BTreeMap<N, usize> {
+        self.predecessors
+            .iter()
+            .map(|(node, preds)| (*node, preds.len()))
+            .collect()
+    }
+
+    /// Returns the nodes in an order in which every node comes after
+    /// all its predecessors.  When more than one node could come next,
+    /// the lowest is chosen.
+    pub fn topological_sort(&self) -> Result<Vec<N>, Cycle<N>> {
+        let mut unmet = self.unmet_dependencies();
+        let mut ready: BTreeSet<N> = unmet
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        let mut order: Vec<N> = Vec::with_capacity(unmet.len());
+        while let Some(node) = ready.pop_first() {
+            order.push(node);
+            for succ in self.successors(node) {
+                if let Some(n) = unmet.get_mut(&succ) {
+                    *n -= 1;
+                    if *n == 0 {
+                        ready.insert(succ);
+                    }
+                }
+            }
+        }
+        if order.len() == unmet.len() {
+            Ok(order)
+        } else {
+            let blocked: BTreeSet<N> = unmet
+                .into_iter()
+                .filter(|(_, n)| *n > 0)
+                .map(|(node, _)| node)
+                .collect();
+            Err(self.find_cycle(&blocked))
+        }
+    }
+
+    /// Finds a cycle among `blocked`, a set of nodes each of which has
+    /// at least one predecessor which is also in `blocked`.
+    fn find_cycle(&self, blocked: &BTreeSet<N>) -> Cycle<N> {
+        // Walking backwards from any blocked node must eventually
+        // revisit a node, since there is always a blocked predecessor.
+        let mut path: Vec<N> = Vec::new();
+        let mut current = match blocked.first() {
+            Some(node) => *node,
+            None => {
+                return Cycle(Vec::new());
+            }
+        };
+        while !path.contains(&current) {
+            path.push(current);
+            current = self
+                .predecessors(current)
+                .find(|pred| blocked.contains(pred))
+                .expect("blocked nodes have blocked predecessors");
+        }
+        let start = path
+            .iter()
+            .position(|node| *node == current)
+            .expect("current is on the path");
+        // The path runs backwards along the edges.
+        let mut cycle: Vec<N> = path[start..].iter().rev().copied().collect();
+        let lowest = cycle
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, node)| **node)
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        cycle.rotate_left(lowest);
+        Cycle(cycle)
+    }
+
+    /// Simulates `workers` workers carrying out the steps of the
+    /// graph, where each step takes `duration(step)` time units and
+    /// cannot start until all its predecessors are finished.  Idle
+    /// workers always take the lowest available step.
+    pub fn schedule<F>(&self, workers: usize, duration: F) -> Result<Schedule<N>, ScheduleError<N>>
+    where
+        F: Fn(N) -> u64,
+    {
+        // Check for cycles first, since otherwise we would simply run
+        // out of work.
+        self.topological_sort()?;
+        if workers == 0 && !self.successors.is_empty() {
+            return Err(ScheduleError::NoWorkers);
+        }
+        let mut unmet = self.unmet_dependencies();
+        let mut ready: BTreeSet<N> = unmet
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(node, _)| *node)
+            .collect();
+        let mut idle: BTreeSet<usize> = (0..workers).collect();
+        // In-progress steps, keyed by finishing time.
+        let mut in_progress: BTreeSet<(u64, N, usize)> = BTreeSet::new();
+        let mut steps: Vec<ScheduledStep<N>> = Vec::with_capacity(unmet.len());
+        let mut now: u64 = 0;
+        loop {
+            while !ready.is_empty() && !idle.is_empty() {
+                let node = ready.pop_first().expect("ready is not empty");
+                let worker = idle.pop_first().expect("idle is not empty");
+                let end = now + duration(node);
+                in_progress.insert((end, node, worker));
+                steps.push(ScheduledStep {
+                    step: node,
+                    worker,
+                    start: now,
+                    end,
+                });
+            }
+            match in_progress.first().copied() {
+                None => break,
+                Some((end, _, _)) => {
+                    now = end;
+                }
+            }
+            while let Some((end, node, worker)) = in_progress.first().copied() {
+                if end != now {
+                    break;
+                }
+                in_progress.pop_first();
+                idle.insert(worker);
+                for succ in self.successors(node) {
+                    if let Some(n) = unmet.get_mut(&succ) {
+                        *n -= 1;
+                        if *n == 0 {
+                            ready.insert(succ);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Schedule {
+            steps,
+            finish_time: now,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledStep<N> {
+    pub step: N,
+    pub worker: usize,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// The result of `Graph::schedule`.  Steps are listed in the order
+/// in which they were started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule<N> {
+    pub steps: Vec<ScheduledStep<N>>,
+    pub finish_time: u64,
+}
+
+#[cfg(test)]
+fn example() -> Graph<char> {
+    let mut g = Graph::new();
+    for (from, to) in [
+        ('C', 'A'),
+        ('C', 'F'),
+        ('A', 'B'),
+        ('A', 'D'),
+        ('B', 'E'),
+        ('D', 'E'),
+        ('F', 'E'),
+    ] {
+        g.add_edge(from, to);
+    }
+    g
+}
+
+#[test]
+fn test_topological_sort() {
+    let g = example();
+    assert_eq!(g.topological_sort(), Ok(vec!['C', 'A', 'B', 'D', 'F', 'E']));
+    let mut g: Graph<u32> = Graph::new();
+    assert_eq!(g.topological_sort(), Ok(vec![]));
+    g.add_node(3);
+    g.add_edge(2, 1);
+    assert_eq!(g.topological_sort(), Ok(vec![2, 1, 3]));
+}
+
+#[test]
+fn test_cycle_detection() {
+    let mut g = example();
+    g.add_edge('E', 'F');
+    match g.topological_sort() {
+        Ok(order) => panic!("expected a cycle, got {order:?}"),
+        Err(cycle) => {
+            assert_eq!(cycle, Cycle(vec!['E', 'F']));
+            assert_eq!(cycle.to_string(), "dependency cycle: E -> F -> E");
+        }
+    }
+    let mut g: Graph<u32> = Graph::new();
+    g.add_edge(1, 1);
+    assert_eq!(g.topological_sort(), Err(Cycle(vec![1])));
+    assert_eq!(
+        g.schedule(2, |_| 1),
+        Err(ScheduleError::Cycle(Cycle(vec![1])))
+    );
+
+    let mut g: Graph<u32> = Graph::new();
+    g.add_edge(0, 3);
+    g.add_edge(3, 1);
+    g.add_edge(1, 2);
+    g.add_edge(2, 3);
+    assert_eq!(g.topological_sort(), Err(Cycle(vec![1, 2, 3])));
+}
+
+#[test]
+fn test_schedule() {
+    let g = example();
+    let duration = |step: char| u64::from(step) - u64::from('A') + 1;
+    let schedule = g.schedule(2, duration).expect("no cycle");
+    assert_eq!(schedule.finish_time, 15);
+    let order: String = schedule.steps.iter().map(|s| s.step).collect();
+    assert_eq!(order, "CAFBDE");
+    assert_eq!(
+        schedule.steps[3],
+        ScheduledStep {
+            step: 'B',
+            worker: 0,
+            start: 4,
+            end: 6,
+        }
+    );
+
+    // One worker does everything in topological order.
+    let schedule = g.schedule(1, duration).expect("no cycle");
+    assert_eq!(schedule.finish_time, 21);
+    let order: String = schedule.steps.iter().map(|s| s.step).collect();
+    assert_eq!(order, "CABDFE");
+
+    // Without workers, nothing can be done.
+    assert_eq!(g.schedule(0, duration), Err(ScheduleError::NoWorkers));
+    // But that's fine if there is nothing to do.
+    let schedule = Graph::<char>::new()
+        .schedule(0, duration)
+        .expect("no cycle");
+    assert!(schedule.steps.is_empty());
+    assert_eq!(schedule.finish_time, 0);
+}
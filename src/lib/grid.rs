@@ -1,4 +1,5 @@
 use std::fmt::{self, Display, Formatter};
+use std::ops::{Add, Sub};
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub enum CompassDirection {
@@ -18,6 +19,39 @@ impl CompassDirection {
             West => East,
         }
     }
+
+    /// Returns the direction after a quarter turn anticlockwise.
+    pub fn turn_left(&self) -> CompassDirection {
+        use CompassDirection::*;
+        match self {
+            North => West,
+            West => South,
+            South => East,
+            East => North,
+        }
+    }
+
+    /// Returns the direction after a quarter turn clockwise.
+    pub fn turn_right(&self) -> CompassDirection {
+        use CompassDirection::*;
+        match self {
+            North => East,
+            East => South,
+            South => West,
+            West => North,
+        }
+    }
+}
+
+#[test]
+fn test_turns() {
+    for d in ALL_MOVE_OPTIONS {
+        assert_eq!(d.turn_left().turn_right(), d);
+        assert_eq!(d.turn_left().turn_left(), d.reversed());
+        assert_eq!(d.turn_right().turn_right(), d.reversed());
+    }
+    assert_eq!(CompassDirection::North.turn_right(), CompassDirection::East);
+    assert_eq!(CompassDirection::North.turn_left(), CompassDirection::West);
 }
 
 impl From<CompassDirection> for char {
@@ -52,6 +86,17 @@ impl Display for Position {
 }
 
 impl Position {
+    /// Returns a key which sorts positions in reading order: top to
+    /// bottom, then left to right.  (The derived ordering sorts by `x`
+    /// first.)
+    pub fn reading_order(&self) -> (i64, i64) {
+        (self.y, self.x)
+    }
+
+    pub fn manhattan(&self, other: &Position) -> i64 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
     pub fn move_direction(&self, d: &CompassDirection) -> Position {
         match d {
             CompassDirection::North => Position {
@@ -115,3 +160,204 @@ where
         _ => None,
     }
 }
+
+/// A dense rectangular grid of cells, with (0, 0) at the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    /// Builds a grid from a character map, one row per line.  Each
+    /// character is converted by `convert`, which is also told where the
+    /// character is.  Lines shorter than the longest one are padded
+    /// with `fill`.
+    pub fn from_text<F, E>(text: &str, fill: T, mut convert: F) -> Result<Grid<T>, E>
+    where
+        T: Clone,
+        F: FnMut(Position, char) -> Result<T, E>,
+    {
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut grid = Grid::new(width, lines.len(), fill);
+        for (y, line) in lines.iter().enumerate() {
+            for (x, ch) in line.chars().enumerate() {
+                grid.cells[y * width + x] = convert(
+                    Position {
+                        x: x as i64,
+                        y: y as i64,
+                    },
+                    ch,
+                )?;
+            }
+        }
+        Ok(grid)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, p: &Position) -> Option<usize> {
+        let x = usize::try_from(p.x).ok().filter(|x| *x < self.width)?;
+        let y = usize::try_from(p.y).ok().filter(|y| *y < self.height)?;
+        Some(y * self.width + x)
+    }
+
+    /// Returns the cell at `p`, or `None` if `p` is outside the grid.
+    pub fn get(&self, p: &Position) -> Option<&T> {
+        self.index(p).map(|i| &self.cells[i])
+    }
+
+    pub fn get_mut(&mut self, p: &Position) -> Option<&mut T> {
+        self.index(p).map(|i| &mut self.cells[i])
+    }
+
+    /// Returns every position in the grid, in reading order.
+    pub fn positions(&self) -> impl Iterator<Item = Position> {
+        let width = self.width as i64;
+        (0..self.height as i64).flat_map(move |y| (0..width).map(move |x| Position { x, y }))
+    }
+}
+
+#[test]
+fn test_grid() {
+    let grid: Grid<char> =
+        Grid::from_text::<_, ()>("ab\nc\ndef\n", ' ', |_, ch| Ok(ch)).expect("no errors");
+    assert_eq!((grid.width(), grid.height()), (3, 3));
+    assert_eq!(grid.get(&Position { x: 1, y: 0 }), Some(&'b'));
+    assert_eq!(grid.get(&Position { x: 2, y: 0 }), Some(&' '));
+    assert_eq!(grid.get(&Position { x: 2, y: 2 }), Some(&'f'));
+    assert_eq!(grid.get(&Position { x: 3, y: 0 }), None);
+    assert_eq!(grid.get(&Position { x: 0, y: -1 }), None);
+    let order: String = grid
+        .positions()
+        .filter_map(|p| grid.get(&p))
+        .filter(|ch| **ch != ' ')
+        .collect();
+    assert_eq!(order, "abcdef");
+
+    let mut grid = Grid::new(2, 1, 0);
+    if let Some(cell) = grid.get_mut(&Position { x: 1, y: 0 }) {
+        *cell = 7;
+    }
+    assert_eq!(grid.get(&Position { x: 1, y: 0 }), Some(&7));
+    assert!(grid.get_mut(&Position { x: 2, y: 0 }).is_none());
+
+    let bad = Grid::from_text("..\n.x", false, |p, ch| match ch {
+        '.' => Ok(true),
+        _ => Err(p),
+    });
+    assert_eq!(bad, Err(Position { x: 1, y: 1 }));
+}
+
+/// A summed-area table (two-dimensional prefix sum) over a grid of
+/// `width` by `height` cells, which gives the sum of the values in
+/// any rectangle in constant time.
+#[derive(Debug, Clone)]
+pub struct SummedAreaTable<T> {
+    width: usize,
+    height: usize,
+    // sums[y * (width + 1) + x] is the sum of all cells above and to
+    // the left of (x, y), so the first row and column are all zero.
+    sums: Vec<T>,
+}
+
+impl<T> SummedAreaTable<T>
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T>,
+{
+    /// Builds the table for the cells whose values are given by
+    /// `value(x, y)`, for `x` in `0..width` and `y` in `0..height`.
+    pub fn new<F>(width: usize, height: usize, value: F) -> SummedAreaTable<T>
+    where
+        F: Fn(usize, usize) -> T,
+    {
+        let stride = width + 1;
+        let mut sums: Vec<T> = vec![T::default(); stride * (height + 1)];
+        for y in 0..height {
+            let mut row_total = T::default();
+            for x in 0..width {
+                row_total = row_total + value(x, y);
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row_total;
+            }
+        }
+        SummedAreaTable {
+            width,
+            height,
+            sums,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the sum of the cells in the `width` by `height`
+    /// rectangle whose top left cell is (`left`, `top`), or `None` if
+    /// the rectangle does not fit in the grid.
+    pub fn rectangle_sum(&self, left: usize, top: usize, width: usize, height: usize) -> Option<T> {
+        let right = left.checked_add(width)?;
+        let bottom = top.checked_add(height)?;
+        if right > self.width || bottom > self.height {
+            return None;
+        }
+        let at = |x: usize, y: usize| self.sums[y * (self.width + 1) + x];
+        Some(at(right, bottom) + at(left, top) - at(left, bottom) - at(right, top))
+    }
+}
+
+#[test]
+fn test_summed_area_table() {
+    let value = |x: usize, y: usize| (x as i64 + 1) * 10 - (y as i64);
+    let table = SummedAreaTable::new(5, 4, value);
+    assert_eq!((table.width(), table.height()), (5, 4));
+    for left in 0..5 {
+        for top in 0..4 {
+            for width in 0..=(5 - left) {
+                for height in 0..=(4 - top) {
+                    let expected: i64 = (left..left + width)
+                        .flat_map(|x| (top..top + height).map(move |y| value(x, y)))
+                        .sum();
+                    assert_eq!(
+                        table.rectangle_sum(left, top, width, height),
+                        Some(expected),
+                        "{width}x{height} at {left},{top}"
+                    );
+                }
+            }
+        }
+    }
+    assert_eq!(table.rectangle_sum(4, 0, 2, 1), None);
+    assert_eq!(table.rectangle_sum(0, 1, 1, 4), None);
+    assert_eq!(table.rectangle_sum(usize::MAX, 0, 2, 1), None);
+
+    let empty: SummedAreaTable<u32> = SummedAreaTable::new(0, 0, |_, _| 1);
+    assert_eq!(empty.rectangle_sum(0, 0, 0, 0), Some(0));
+    assert_eq!(empty.rectangle_sum(0, 0, 1, 1), None);
+}
@@ -1,3 +1,7 @@
+// Some of the tests below use `matches!` on a `Result`, which newer
+// versions of clippy would rather see written as `is_err()`.
+#![cfg_attr(test, allow(clippy::redundant_pattern_matching))]
+
 pub fn sum_result<T, Q, E>(total: T, current: Result<Q, E>) -> Result<T, E>
 where
     T: std::ops::Add<Output = T>,
@@ -18,6 +22,32 @@ where
     }
 }
 
+/// Addition which reports overflow instead of panicking or wrapping.
+pub trait CheckedAdd: Sized {
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked_add {
+    ($($t:ty)*) => {
+        $(
+            impl CheckedAdd for $t {
+                fn checked_add(self, other: $t) -> Option<$t> {
+                    <$t>::checked_add(self, other)
+                }
+            }
+        )*
+    };
+}
+
+impl_checked_add!(i8 i16 i32 i64 i128 isize u8 u16 u32 u64 u128 usize);
+
+#[cfg(feature = "bigint")]
+impl CheckedAdd for num_bigint::BigInt {
+    fn checked_add(self, other: num_bigint::BigInt) -> Option<num_bigint::BigInt> {
+        Some(self + other)
+    }
+}
+
 #[test]
 fn test_sum_result() {
     let input: Vec<Result<i32, ()>> = vec![Ok(1), Ok(2), Ok(800)];
@@ -69,3 +99,10 @@ fn test_sum_result_propagate_error() {
         .try_fold(0_i64, sum_result);
     assert!(matches!(total, Err(_)), "{total:?}");
 }
+
+#[test]
+fn test_checked_add() {
+    assert_eq!(CheckedAdd::checked_add(100_i8, 27), Some(127));
+    assert_eq!(CheckedAdd::checked_add(100_i8, 28), None);
+    assert_eq!(CheckedAdd::checked_add(0_u64, u64::MAX), Some(u64::MAX));
+}
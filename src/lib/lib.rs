@@ -1,4 +1,12 @@
+pub mod automaton;
+pub mod debugger;
+pub mod disasm;
+pub mod elfcode;
 pub mod error;
+pub mod graph;
 pub mod grid;
 pub mod input;
 pub mod iterplus;
+pub mod ocr;
+pub mod ring;
+pub mod strdist;
@@ -0,0 +1,285 @@
+//! Recognition of the block letters which some puzzles draw with
+//! lit pixels.
+//!
+//! The letters are 6 pixels wide and 10 high, and are separated by
+//! at least one blank column.
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::error::Fail;
+
+pub const LETTER_WIDTH: usize = 6;
+pub const LETTER_HEIGHT: usize = 10;
+
+static FONT: [(char, [&str; LETTER_HEIGHT]); 15] = [
+    (
+        'A',
+        [
+            "..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'B',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#",
+            "#....#", "#####.",
+        ],
+    ),
+    (
+        'C',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#....#", ".####.",
+        ],
+    ),
+    (
+        'E',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'F',
+        [
+            "######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'G',
+        [
+            ".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#",
+            "#...##", ".###.#",
+        ],
+    ),
+    (
+        'H',
+        [
+            "#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'J',
+        [
+            "...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.",
+            "#...#.", ".###..",
+        ],
+    ),
+    (
+        'K',
+        [
+            "#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..",
+            "#...#.", "#....#",
+        ],
+    ),
+    (
+        'L',
+        [
+            "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+    (
+        'N',
+        [
+            "#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##",
+            "#...##", "#....#",
+        ],
+    ),
+    (
+        'P',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....",
+            "#.....", "#.....",
+        ],
+    ),
+    (
+        'R',
+        [
+            "#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'X',
+        [
+            "#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.",
+            "#....#", "#....#",
+        ],
+    ),
+    (
+        'Z',
+        [
+            "######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....",
+            "#.....", "######",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// The lit pixels span the wrong number of rows.
+    WrongHeight { height: usize },
+    /// The pixels starting at `column` are not a letter we know.
+    /// `glyph` shows them, using '#' for lit pixels and '.' for
+    /// unlit ones.
+    UnknownLetter { column: usize, glyph: String },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::WrongHeight { height } => write!(
+                f,
+                "text is {height} pixels high but letters are {LETTER_HEIGHT} pixels high"
+            ),
+            OcrError::UnknownLetter { column, glyph } => {
+                write!(f, "unrecognised letter at column {column}:\n{glyph}")
+            }
+        }
+    }
+}
+
+impl Error for OcrError {}
+
+impl From<OcrError> for Fail {
+    fn from(e: OcrError) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+/// Returns the pattern for `letter`, or `None` if the font does not
+/// include it.
+pub fn glyph(letter: char) -> Option<&'static [&'static str; LETTER_HEIGHT]> {
+    FONT.iter()
+        .find(|(ch, _)| *ch == letter)
+        .map(|(_, rows)| rows)
+}
+
+/// Reads the letters drawn in `picture`, in which '#' is a lit pixel
+/// and any other character is unlit.  Blank rows above and below the
+/// text, and blank columns around or between letters, are ignored.
+pub fn recognise(picture: &str) -> Result<String, OcrError> {
+    let mut rows: Vec<Vec<bool>> = picture
+        .lines()
+        .map(|line| line.chars().map(|ch| ch == '#').collect())
+        .collect();
+    let is_blank = |row: &Vec<bool>| !row.iter().any(|lit| *lit);
+    while rows.last().is_some_and(is_blank) {
+        rows.pop();
+    }
+    let first_lit_row = rows.iter().position(|row| !is_blank(row));
+    let rows: &[Vec<bool>] = match first_lit_row {
+        Some(first) => &rows[first..],
+        None => {
+            return Ok(String::new());
+        }
+    };
+    if rows.len() != LETTER_HEIGHT {
+        return Err(OcrError::WrongHeight { height: rows.len() });
+    }
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |x: usize, y: usize| rows[y].get(x).copied().unwrap_or(false);
+    let column_is_blank = |x: usize| (0..LETTER_HEIGHT).all(|y| !lit(x, y));
+
+    let mut result = String::new();
+    let mut x = 0;
+    while x < width {
+        if column_is_blank(x) {
+            x += 1;
+            continue;
+        }
+        let start = x;
+        while x < width && !column_is_blank(x) {
+            x += 1;
+        }
+        let pattern: Vec<String> = (0..LETTER_HEIGHT)
+            .map(|y| {
+                (start..x)
+                    .map(|x| if lit(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        match FONT.iter().find(|(_, rows)| rows[..] == pattern[..]) {
+            Some((letter, _)) => result.push(*letter),
+            None => {
+                return Err(OcrError::UnknownLetter {
+                    column: start,
+                    glyph: pattern.join("\n"),
+                });
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+fn draw(text: &str, gap: usize) -> String {
+    let mut rows: Vec<String> = vec![String::new(); LETTER_HEIGHT];
+    for letter in text.chars() {
+        let pattern = glyph(letter).expect("letter should be in the font");
+        for (row, pixels) in rows.iter_mut().zip(pattern.iter()) {
+            row.push_str(pixels);
+            row.push_str(&".".repeat(gap));
+        }
+    }
+    rows.join("\n")
+}
+
+#[test]
+fn test_font() {
+    for (letter, rows) in FONT.iter() {
+        assert!(
+            rows.iter().all(|row| row.len() == LETTER_WIDTH),
+            "letter {letter} is the wrong width"
+        );
+        // Every column is used, otherwise we would split the letter
+        // in two.
+        for x in 0..LETTER_WIDTH {
+            assert!(
+                rows.iter().any(|row| row.as_bytes()[x] == b'#'),
+                "letter {letter} has a blank column {x}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_recognise() {
+    let all: String = FONT.iter().map(|(letter, _)| *letter).collect();
+    assert_eq!(recognise(&draw(&all, 2)), Ok(all.clone()));
+    assert_eq!(recognise(&draw(&all, 1)), Ok(all));
+    let padded = format!("......\n\n{}\n........\n", draw("HELP", 3));
+    assert_eq!(recognise(&padded), Ok("HELP".to_string()));
+    assert_eq!(recognise(""), Ok(String::new()));
+    assert_eq!(recognise("...\n..."), Ok(String::new()));
+}
+
+#[test]
+fn test_recognise_errors() {
+    assert_eq!(
+        recognise("#\n#\n#"),
+        Err(OcrError::WrongHeight { height: 3 })
+    );
+    // Two letters with no gap between them.
+    assert_eq!(
+        recognise(&draw("LL", 0)),
+        Err(OcrError::UnknownLetter {
+            column: 0,
+            glyph: draw("LL", 0),
+        })
+    );
+    let mut picture = draw("HEL", 2);
+    // Turn the E into something unrecognisable.
+    picture.replace_range(8..9, ".");
+    match recognise(&picture) {
+        Err(OcrError::UnknownLetter { column, glyph }) => {
+            assert_eq!(column, 8);
+            assert!(glyph.starts_with(".#####\n#....."), "{glyph}");
+        }
+        other => panic!("expected an unknown letter, got {other:?}"),
+    }
+}
@@ -0,0 +1,203 @@
+//! A circular list with a cursor.
+use std::collections::VecDeque;
+use std::iter::FromIterator;
+
+/// A circular sequence of items with a cursor pointing at one of
+/// them (the "current" item).  Inserting and removing at the cursor
+/// take constant time, and moving the cursor by `k` takes time
+/// proportional to `k` (or to the length of the ring, if that's
+/// smaller).
+///
+/// Internally the current item is always at the front of a
+/// `VecDeque`, and the clockwise direction runs from front to back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ring<T> {
+    items: VecDeque<T>,
+}
+
+impl<T> Default for Ring<T> {
+    fn default() -> Ring<T> {
+        Ring::new()
+    }
+}
+
+impl<T> Ring<T> {
+    pub fn new() -> Ring<T> {
+        Ring {
+            items: VecDeque::new(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Ring<T> {
+        Ring {
+            items: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the item under the cursor.
+    pub fn current(&self) -> Option<&T> {
+        self.items.front()
+    }
+
+    /// Moves the cursor `k` places clockwise (or anticlockwise, if
+    /// `k` is negative).  Rotating an empty ring does nothing.
+    ///
+    /// This takes O(min(k, n − k)) time for a ring of `n` items, not
+    /// O(1): it moves that many items from one end of the deque to
+    /// the other.  A linked ring would do no better, since it must
+    /// still follow `k` links.  Day 9 only ever rotates by 2 or 7, so
+    /// there each rotation costs constant time.
+    pub fn rotate(&mut self, k: isize) {
+        let n = self.items.len();
+        if n == 0 {
+            return;
+        }
+        let k = k.rem_euclid(n as isize) as usize;
+        if k <= n / 2 {
+            self.items.rotate_left(k);
+        } else {
+            self.items.rotate_right(n - k);
+        }
+    }
+
+    /// Inserts `value` immediately clockwise of the current item, and
+    /// moves the cursor onto it.
+    pub fn insert(&mut self, value: T) {
+        // Moving the current item to the back puts it just
+        // anticlockwise of whatever we push onto the front.
+        if let Some(current) = self.items.pop_front() {
+            self.items.push_back(current);
+        }
+        self.items.push_front(value);
+    }
+
+    /// Removes and returns the current item.  The cursor moves to the
+    /// item which was clockwise of it.
+    pub fn remove(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+
+    /// Iterates over the items clockwise, starting at the cursor.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.items.iter()
+    }
+}
+
+/// Builds a ring whose items are in clockwise order, with the cursor
+/// on the first item.
+impl<T> FromIterator<T> for Ring<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Ring<T> {
+        Ring {
+            items: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+fn contents<T: Copy>(ring: &Ring<T>) -> Vec<T> {
+    ring.iter().copied().collect()
+}
+
+#[test]
+fn test_empty() {
+    let mut ring: Ring<u32> = Ring::new();
+    assert!(ring.is_empty());
+    assert_eq!(ring.current(), None);
+    ring.rotate(3);
+    ring.rotate(-3);
+    assert_eq!(ring.remove(), None);
+    assert_eq!(ring.len(), 0);
+}
+
+#[test]
+fn test_rotate() {
+    let mut ring: Ring<u32> = (0..5).collect();
+    assert_eq!(ring.current(), Some(&0));
+    ring.rotate(2);
+    assert_eq!(contents(&ring), vec![2, 3, 4, 0, 1]);
+    ring.rotate(-3);
+    assert_eq!(contents(&ring), vec![4, 0, 1, 2, 3]);
+    ring.rotate(11);
+    assert_eq!(ring.current(), Some(&0));
+    ring.rotate(-16);
+    assert_eq!(ring.current(), Some(&4));
+    ring.rotate(0);
+    assert_eq!(ring.current(), Some(&4));
+}
+
+#[test]
+fn test_insert_remove() {
+    let mut ring: Ring<char> = Ring::with_capacity(4);
+    ring.insert('a');
+    assert_eq!(contents(&ring), vec!['a']);
+    ring.insert('b');
+    ring.insert('c');
+    assert_eq!(contents(&ring), vec!['c', 'a', 'b']);
+    ring.rotate(1);
+    ring.insert('d');
+    // Clockwise: a b c, with d inserted after a.
+    assert_eq!(contents(&ring), vec!['d', 'b', 'c', 'a']);
+    assert_eq!(ring.remove(), Some('d'));
+    assert_eq!(ring.current(), Some(&'b'));
+    ring.rotate(-1);
+    assert_eq!(ring.remove(), Some('a'));
+    assert_eq!(contents(&ring), vec!['b', 'c']);
+    assert_eq!(ring.len(), 2);
+}
+
+#[test]
+fn test_against_vec() {
+    use rand::{Rng, SeedableRng};
+    // Compare against a simple (but slow) model: a vector and an index.
+    let mut rng = rand::rngs::StdRng::seed_from_u64(20181209);
+    let mut ring: Ring<u32> = Ring::new();
+    let mut model: Vec<u32> = Vec::new();
+    let mut cursor: usize = 0;
+    for value in 0..2000 {
+        match rng.gen_range(0..3) {
+            0 => {
+                let k: isize = rng.gen_range(-20..20);
+                ring.rotate(k);
+                if !model.is_empty() {
+                    cursor = (cursor as isize + k).rem_euclid(model.len() as isize) as usize;
+                }
+            }
+            1 => {
+                ring.insert(value);
+                if model.is_empty() {
+                    model.push(value);
+                    cursor = 0;
+                } else {
+                    cursor += 1;
+                    model.insert(cursor, value);
+                }
+            }
+            _ => {
+                let expected = if model.is_empty() {
+                    None
+                } else {
+                    let v = model.remove(cursor);
+                    if cursor == model.len() {
+                        cursor = 0;
+                    }
+                    Some(v)
+                };
+                assert_eq!(ring.remove(), expected);
+            }
+        }
+        let expected: Vec<u32> = model[cursor..]
+            .iter()
+            .chain(model[..cursor].iter())
+            .copied()
+            .collect();
+        assert_eq!(contents(&ring), expected);
+    }
+}
@@ -0,0 +1,194 @@
+//! Distances between strings.
+//!
+//! All of these functions work on extended grapheme clusters rather
+//! than bytes or `char`s, so "e" followed by a combining acute accent
+//! counts as a single letter, just like "é".
+use std::cmp::min;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::Fail;
+
+/// Two strings which were required to be the same length were not.
+/// The lengths are counted in graphemes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthMismatch {
+    pub left: usize,
+    pub right: usize,
+}
+
+impl Display for LengthMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "strings have different lengths ({} and {} letters)",
+            self.left, self.right
+        )
+    }
+}
+
+impl Error for LengthMismatch {}
+
+impl From<LengthMismatch> for Fail {
+    fn from(e: LengthMismatch) -> Fail {
+        Fail(e.to_string())
+    }
+}
+
+fn letters(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+/// Returns the number of positions at which `left` and `right`
+/// differ.  Strings of different lengths have no Hamming distance.
+pub fn hamming(left: &str, right: &str) -> Result<usize, LengthMismatch> {
+    let (l, r) = (letters(left), letters(right));
+    if l.len() != r.len() {
+        return Err(LengthMismatch {
+            left: l.len(),
+            right: r.len(),
+        });
+    }
+    Ok(l.iter().zip(r.iter()).filter(|(a, b)| a != b).count())
+}
+
+#[test]
+fn test_hamming() {
+    assert_eq!(hamming("", ""), Ok(0));
+    assert_eq!(hamming("abcde", "axcye"), Ok(2));
+    assert_eq!(hamming("fghij", "fguij"), Ok(1));
+    assert_eq!(
+        hamming("abc", "abcdef"),
+        Err(LengthMismatch { left: 3, right: 6 })
+    );
+    // Precomposed and decomposed forms are still different letters,
+    // but each is only one letter.
+    assert_eq!(hamming("caf\u{e9}", "cafe\u{301}"), Ok(1));
+    assert_eq!(hamming("cafe\u{301}", "cafe"), Ok(1));
+}
+
+/// Returns the minimum number of single-letter insertions, deletions
+/// and substitutions needed to turn `left` into `right`.
+pub fn levenshtein(left: &str, right: &str) -> usize {
+    let (l, r) = (letters(left), letters(right));
+    let mut previous: Vec<usize> = (0..=r.len()).collect();
+    let mut current: Vec<usize> = vec![0; r.len() + 1];
+    for (i, a) in l.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, b) in r.iter().enumerate() {
+            let cost = if a == b { 0 } else { 1 };
+            current[j + 1] = min(previous[j] + cost, min(previous[j + 1] + 1, current[j] + 1));
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[r.len()]
+}
+
+#[test]
+fn test_levenshtein() {
+    assert_eq!(levenshtein("", ""), 0);
+    assert_eq!(levenshtein("", "abc"), 3);
+    assert_eq!(levenshtein("abc", ""), 3);
+    assert_eq!(levenshtein("kitten", "sitting"), 3);
+    assert_eq!(levenshtein("abc", "abcdef"), 3);
+    assert_eq!(levenshtein("ab", "ba"), 2);
+    assert_eq!(levenshtein("Łódź", "Lodz"), 3);
+}
+
+/// Like `levenshtein`, but swapping two adjacent letters also counts
+/// as a single edit.  This is the unrestricted form of the distance,
+/// so letters may be edited again after they have been swapped.
+pub fn damerau_levenshtein(left: &str, right: &str) -> usize {
+    let (l, r) = (letters(left), letters(right));
+    let infinity = l.len() + r.len();
+    // d[i + 1][j + 1] is the distance between the first i letters of
+    // `left` and the first j letters of `right`.
+    let mut d: Vec<Vec<usize>> = vec![vec![0; r.len() + 2]; l.len() + 2];
+    d[0][0] = infinity;
+    for i in 0..=l.len() {
+        d[i + 1][0] = infinity;
+        d[i + 1][1] = i;
+    }
+    for j in 0..=r.len() {
+        d[0][j + 1] = infinity;
+        d[1][j + 1] = j;
+    }
+    // The last row of `left` in which each letter was seen.
+    let mut last_row: HashMap<&str, usize> = HashMap::new();
+    for i in 1..=l.len() {
+        let mut last_match_column = 0;
+        for j in 1..=r.len() {
+            let i1 = last_row.get(r[j - 1]).copied().unwrap_or(0);
+            let j1 = last_match_column;
+            let cost = if l[i - 1] == r[j - 1] {
+                last_match_column = j;
+                0
+            } else {
+                1
+            };
+            d[i + 1][j + 1] = min(
+                min(d[i][j] + cost, d[i + 1][j] + 1),
+                min(d[i][j + 1] + 1, d[i1][j1] + (i - i1 - 1) + 1 + (j - j1 - 1)),
+            );
+        }
+        last_row.insert(l[i - 1], i);
+    }
+    d[l.len() + 1][r.len() + 1]
+}
+
+#[test]
+fn test_damerau_levenshtein() {
+    assert_eq!(damerau_levenshtein("", ""), 0);
+    assert_eq!(damerau_levenshtein("", "abc"), 3);
+    assert_eq!(damerau_levenshtein("kitten", "sitting"), 3);
+    assert_eq!(damerau_levenshtein("ab", "ba"), 1);
+    // The restricted ("optimal string alignment") distance would be 3.
+    assert_eq!(damerau_levenshtein("ca", "abc"), 2);
+    assert_eq!(damerau_levenshtein("ó\u{301}x", "xó\u{301}"), 1);
+}
+
+/// Returns the letters which all of `ids` have in common at the same
+/// position, in order.  All the strings must be the same length.
+pub fn common_letters(ids: &[&str]) -> Result<String, LengthMismatch> {
+    let split: Vec<Vec<&str>> = ids.iter().map(|s| letters(s)).collect();
+    let first: &[&str] = match split.first() {
+        Some(first) => first,
+        None => {
+            return Ok(String::new());
+        }
+    };
+    if let Some(other) = split.iter().find(|other| other.len() != first.len()) {
+        return Err(LengthMismatch {
+            left: first.len(),
+            right: other.len(),
+        });
+    }
+    Ok(first
+        .iter()
+        .enumerate()
+        .filter(|(pos, letter)| split.iter().all(|other| other[*pos] == **letter))
+        .map(|(_, letter)| *letter)
+        .collect())
+}
+
+#[test]
+fn test_common_letters() {
+    assert_eq!(common_letters(&[]), Ok(String::new()));
+    assert_eq!(common_letters(&["abc"]), Ok("abc".to_string()));
+    assert_eq!(common_letters(&["fghij", "fguij"]), Ok("fgij".to_string()));
+    assert_eq!(
+        common_letters(&["abcde", "axcye", "abcyz"]),
+        Ok("ac".to_string())
+    );
+    assert_eq!(
+        common_letters(&["abc", "abcdef"]),
+        Err(LengthMismatch { left: 3, right: 6 })
+    );
+    assert_eq!(
+        common_letters(&["cafe\u{301}s", "cafés"]),
+        Ok("cafs".to_string())
+    );
+}